@@ -0,0 +1,110 @@
+// Compare the Amount backends with
+//
+//   cargo bench --bench amounts -- --save-baseline decimal
+//   cargo bench --bench amounts --features fixed-point -- --baseline decimal
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use csv::{ReaderBuilder, StringRecord, Trim};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use uosckjwncs::*;
+
+const ROWS: u64 = 10_000_000;
+
+// The same mix of rows every run, so both backends see the same file
+fn generate(path: &Path) {
+    let mut writer = BufWriter::new(File::create(path).unwrap());
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+
+    writeln!(writer, "type,client,tx,amount").unwrap();
+
+    for tx in 1..=ROWS {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+
+        let client = seed % 1000;
+        let amount = format!("{}.{:04}", (seed >> 10) % 1000, (seed >> 20) % 10000);
+
+        match seed % 100 {
+            0 => writeln!(writer, "dispute,{client},{}", tx - 1),
+            1 => writeln!(writer, "resolve,{client},{}", tx - 2),
+            2..=39 => writeln!(writer, "withdrawal,{client},{tx},{amount}"),
+            _ => writeln!(writer, "deposit,{client},{tx},{amount}"),
+        }
+        .unwrap();
+    }
+}
+
+fn input() -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("amounts.csv");
+
+    if !path.exists() {
+        generate(&path);
+    }
+
+    path
+}
+
+fn process(path: &Path) -> Ledger {
+    let mut config = Config::new();
+    // Keeps memory flat however many rows there are
+    config.dispute_policy.window = Some(100_000);
+
+    let mut ledger = Ledger::with_config(config);
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .trim(Trim::All)
+        .from_path(path)
+        .unwrap();
+    let mut record = StringRecord::new();
+
+    while reader.read_record(&mut record).unwrap() {
+        let _ = ledger.apply_record(&record);
+        ledger.warnings.clear();
+    }
+
+    ledger
+}
+
+fn parse(c: &mut Criterion) {
+    let amounts: Vec<String> = (0..1000)
+        .map(|n| format!("{}.{:04}", n * 7919 % 100_000, n * 104_729 % 10_000))
+        .collect();
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(amounts.len() as u64));
+    group.bench_function("from_str", |b| {
+        b.iter(|| {
+            for amount in &amounts {
+                black_box(Amount::from_str(black_box(amount)).unwrap());
+            }
+        })
+    });
+    group.bench_function("round", |b| {
+        let money = Money::new();
+
+        b.iter(|| {
+            for amount in &amounts {
+                black_box(money.round(Amount::from_str(black_box(amount)).unwrap()));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn ledger(c: &mut Criterion) {
+    let path = input();
+
+    let mut group = c.benchmark_group("ledger");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(150));
+    group.throughput(Throughput::Elements(ROWS));
+    group.bench_function("10m_rows", |b| b.iter(|| black_box(process(&path))));
+    group.finish();
+}
+
+criterion_group!(benches, parse, ledger);
+criterion_main!(benches);
@@ -1,90 +1,485 @@
 use super::*;
 
-use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, PartialEq)]
-pub struct Account {
+// One currency's balances within an account
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wallet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
     pub available: Amount,
     pub held: Amount,
-    pub locked: bool,
+    pub debt: Amount,
+}
+
+impl Wallet {
+    pub fn new(currency: Option<Currency>) -> Self {
+        Wallet {
+            currency,
+            available: Amount::ZERO,
+            held: Amount::ZERO,
+            debt: Amount::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedAccount")]
+pub struct Account {
+    // Ordered by currency, the unnamed wallet first
+    pub wallets: Vec<Wallet>,
+    pub status: AccountStatus,
+    pub credit_limit: Amount,
+    // Transactions in other currencies are converted into this one, if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_currency: Option<Currency>,
+}
+
+// Snapshots before version 3 held a single set of balances per account
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedAccount {
+    Wallets {
+        wallets: Vec<Wallet>,
+        status: AccountStatus,
+        credit_limit: Amount,
+        // Only in version 4 onwards
+        #[serde(default)]
+        base_currency: Option<Currency>,
+    },
+    Single {
+        available: Amount,
+        held: Amount,
+        status: AccountStatus,
+        credit_limit: Amount,
+        debt: Amount,
+    },
+}
+
+impl From<SavedAccount> for Account {
+    fn from(saved_account: SavedAccount) -> Self {
+        match saved_account {
+            SavedAccount::Wallets {
+                wallets,
+                status,
+                credit_limit,
+                base_currency,
+            } => Account {
+                wallets,
+                status,
+                credit_limit,
+                base_currency,
+            },
+            SavedAccount::Single {
+                available,
+                held,
+                status,
+                credit_limit,
+                debt,
+            } => Account {
+                wallets: vec![Wallet {
+                    currency: None,
+                    available,
+                    held,
+                    debt,
+                }],
+                status,
+                credit_limit,
+                base_currency: None,
+            },
+        }
+    }
 }
 
 impl Account {
-    pub fn deposit(&mut self, transaction: &Transaction, transaction_logs: &mut TransactionLogs) {
-        if !transaction_logs.contains_key(&transaction.id) {
-            transaction_logs.add(transaction);
+    pub fn new() -> Self {
+        Account {
+            wallets: Vec::new(),
+            status: AccountStatus::Active,
+            credit_limit: Amount::ZERO,
+            base_currency: None,
+        }
+    }
 
-            // Assuming we don't want to skip/panic on overflow, let's saturate
-            self.available = self.available.saturating_add(transaction.amount);
+    // The wallet for the currency, empty if nothing has been held in it yet
+    pub fn wallet(&self, currency: Option<Currency>) -> Wallet {
+        self.wallets
+            .iter()
+            .find(|wallet| wallet.currency == currency)
+            .copied()
+            .unwrap_or_else(|| Wallet::new(currency))
+    }
+
+    pub fn set_wallet(&mut self, wallet: Wallet) {
+        match self
+            .wallets
+            .binary_search_by_key(&wallet.currency, |wallet| wallet.currency)
+        {
+            Ok(index) => self.wallets[index] = wallet,
+            Err(index) => self.wallets.insert(index, wallet),
+        }
+    }
+
+    // The wallets to report, an account without any reported as empty
+    pub fn balances(&self) -> Vec<Wallet> {
+        if self.wallets.is_empty() {
+            vec![Wallet::new(None)]
+        } else {
+            self.wallets.clone()
+        }
+    }
+
+    pub fn deposit(
+        &mut self,
+        transaction: &Transaction,
+        transaction_logs: &mut TransactionLogs,
+        config: &Config,
+        warnings: &mut Warnings,
+    ) -> Result<(), TransactionError> {
+        if transaction_logs.contains(transaction.id)? {
+            return Err(TransactionError::DuplicateTransaction);
         }
+
+        let transaction_log = self.settlement(transaction, config)?;
+        let mut wallet = self.wallet(transaction_log.currency);
+
+        let overflow = config.overflow_policy;
+        let available = overflow.add(
+            wallet.available,
+            transaction_log.amount,
+            transaction,
+            warnings,
+        )?;
+
+        transaction_logs.insert(transaction.id, transaction_log)?;
+
+        // Deposits pay off any debt first
+        wallet.debt = wallet.debt.min(shortfall(available, wallet.held));
+        wallet.available = available;
+
+        self.set_wallet(wallet);
+
+        Ok(())
     }
 
     pub fn withdrawal(
         &mut self,
         transaction: &Transaction,
         transaction_logs: &mut TransactionLogs,
-    ) {
-        if !transaction_logs.contains_key(&transaction.id) {
-            transaction_logs.add(transaction);
+        config: &Config,
+        warnings: &mut Warnings,
+    ) -> Result<(), TransactionError> {
+        if transaction_logs.contains(transaction.id)? {
+            return Err(TransactionError::DuplicateTransaction);
+        }
 
-            // Assuming we don't want to skip/panic on underflow, let's saturate
-            if self.available.saturating_sub(transaction.amount) >= Decimal::ZERO {
-                self.available = self.available.saturating_sub(transaction.amount);
-            }
+        let transaction_log = self.settlement(transaction, config)?;
+        let mut wallet = self.wallet(transaction_log.currency);
+
+        // An underflow can only mean the funds are insufficient
+        match wallet.available.checked_sub(transaction_log.amount) {
+            Some(available) if available >= -self.credit_limit => {}
+            _ => return Err(TransactionError::InsufficientFunds),
         }
+
+        let overflow = config.overflow_policy;
+        let available = overflow.sub(
+            wallet.available,
+            transaction_log.amount,
+            transaction,
+            warnings,
+        )?;
+
+        transaction_logs.insert(transaction.id, transaction_log)?;
+
+        wallet.available = available;
+
+        self.set_wallet(wallet);
+
+        Ok(())
     }
 
     pub fn dispute(
         &mut self,
         transaction: &Transaction,
         transaction_logs: &mut TransactionLogs,
-        disputes: &mut Disputes,
-    ) {
-        if let Some(transaction_log) = transaction_logs.find(transaction) {
-            // Assuming we don't want to skip/panic on underflow/overflow, let's saturate
-            self.available = self.available.saturating_sub(transaction_log.amount);
-            self.held = self.held.saturating_add(transaction_log.amount);
-
-            disputes.insert(transaction.id);
-        }
+        config: &Config,
+        warnings: &mut Warnings,
+    ) -> Result<(), TransactionError> {
+        let transaction_log = transaction_logs.find(transaction)?;
+        let mut wallet = self.disputed_wallet(transaction, &transaction_log)?;
+        let state = transaction_log
+            .state
+            .transition(TransactionKind::Dispute, &config.dispute_policy)?;
+
+        let overflow = config.overflow_policy;
+        let amount = transaction_log.amount;
+
+        // Work out every new balance up front, so a rejection leaves us untouched
+        let (available, held) = match config.dispute_policy.mode(transaction_log.kind) {
+            DisputeMode::HoldFunds => (
+                overflow.sub(wallet.available, amount, transaction, warnings)?,
+                overflow.add(wallet.held, amount, transaction, warnings)?,
+            ),
+            DisputeMode::PendingCredit => (
+                wallet.available,
+                overflow.add(wallet.held, amount, transaction, warnings)?,
+            ),
+            DisputeMode::Refuse => return Err(TransactionError::DisputeNotAllowed),
+        };
+
+        wallet.available = available;
+        wallet.held = held;
+
+        transaction_logs.set_state(transaction.id, state)?;
+
+        self.set_wallet(wallet);
+
+        Ok(())
     }
 
     pub fn resolve(
         &mut self,
         transaction: &Transaction,
         transaction_logs: &mut TransactionLogs,
-        disputes: &mut Disputes,
-    ) {
-        if let Some(transaction_log) = transaction_logs.find(transaction) {
-            if disputes.remove(&transaction.id) {
-                // Assuming we don't want to skip/panic on underflow/overflow, let's saturate
-                self.held = self.held.saturating_sub(transaction_log.amount);
-                self.available = self.available.saturating_add(transaction_log.amount);
-            }
+        config: &Config,
+        warnings: &mut Warnings,
+    ) -> Result<(), TransactionError> {
+        let transaction_log = transaction_logs.find(transaction)?;
+        let mut wallet = self.disputed_wallet(transaction, &transaction_log)?;
+        let mode = config.dispute_policy.mode(transaction_log.kind);
+
+        if mode == DisputeMode::Refuse {
+            return Err(TransactionError::DisputeNotAllowed);
         }
+
+        let state = transaction_log
+            .state
+            .transition(TransactionKind::Resolve, &config.dispute_policy)?;
+
+        let overflow = config.overflow_policy;
+        let amount = transaction_log.amount;
+
+        let held = overflow.sub(wallet.held, amount, transaction, warnings)?;
+        let available = if mode == DisputeMode::HoldFunds {
+            overflow.add(wallet.available, amount, transaction, warnings)?
+        } else {
+            wallet.available
+        };
+
+        wallet.available = available;
+        wallet.held = held;
+
+        transaction_logs.set_state(transaction.id, state)?;
+
+        self.set_wallet(wallet);
+
+        Ok(())
     }
 
     pub fn chargeback(
         &mut self,
         transaction: &Transaction,
         transaction_logs: &mut TransactionLogs,
-        disputes: &mut Disputes,
-    ) {
-        if let Some(transaction_log) = transaction_logs.find(transaction) {
-            if disputes.remove(&transaction.id) {
-                // Assuming we don't want to skip/panic on underflow, let's saturate
-                self.held = self.held.saturating_sub(transaction_log.amount);
-
-                self.locked = true;
+        config: &Config,
+        warnings: &mut Warnings,
+    ) -> Result<(), TransactionError> {
+        let transaction_log = transaction_logs.find(transaction)?;
+        let mut wallet = self.disputed_wallet(transaction, &transaction_log)?;
+        let mode = config.dispute_policy.mode(transaction_log.kind);
+
+        if mode == DisputeMode::Refuse {
+            return Err(TransactionError::DisputeNotAllowed);
+        }
+
+        let state = transaction_log
+            .state
+            .transition(TransactionKind::Chargeback, &config.dispute_policy)?;
+
+        let overflow = config.overflow_policy;
+        let amount = transaction_log.amount;
+
+        let held = overflow.sub(wallet.held, amount, transaction, warnings)?;
+        let available = if mode == DisputeMode::PendingCredit {
+            // Reversing a withdrawal credits the client
+            overflow.add(wallet.available, amount, transaction, warnings)?
+        } else {
+            wallet.available
+        };
+
+        // Whatever the chargeback leaves uncovered is owed by the client
+        let debt = if mode == DisputeMode::HoldFunds {
+            overflow.add(wallet.debt, amount, transaction, warnings)?
+        } else {
+            wallet.debt
+        };
+
+        wallet.available = available;
+        wallet.held = held;
+        wallet.debt = debt.min(shortfall(available, held));
+
+        // A chargeback in any currency locks the whole account
+        self.status = AccountStatus::Locked;
+
+        transaction_logs.set_state(transaction.id, state)?;
+
+        self.set_wallet(wallet);
+
+        Ok(())
+    }
+
+    pub fn change_status(&mut self, transaction: &Transaction) -> Result<(), TransactionError> {
+        let status = self.status.transition(transaction.kind)?;
+
+        if status == AccountStatus::Closed {
+            // Nothing can be resolved or charged back once closed, so closing
+            // would leave held funds stuck
+            if self
+                .wallets
+                .iter()
+                .any(|wallet| wallet.held != Amount::ZERO)
+            {
+                return Err(TransactionError::DisputesOpen);
+            }
+
+            // Nor could a balance be paid out or a debt be settled
+            if self
+                .wallets
+                .iter()
+                .any(|wallet| wallet.available != Amount::ZERO || wallet.debt != Amount::ZERO)
+            {
+                return Err(TransactionError::BalanceNotZero);
+            }
+        }
+
+        self.status = status;
+
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        // Anything other than an active account refuses client transactions
+        self.status != AccountStatus::Active
+    }
+
+    // What a deposit or withdrawal moves in and out of its wallet. Without a
+    // currency it is in the base currency, and in any other it is converted
+    // into the base currency at the rate in force when it was made.
+    fn settlement(
+        &self,
+        transaction: &Transaction,
+        config: &Config,
+    ) -> Result<TransactionLog, TransactionError> {
+        let mut transaction_log = TransactionLog::new(transaction);
+
+        match (transaction.currency, self.base_currency) {
+            (Some(currency), Some(base_currency)) if currency != base_currency => {
+                transaction_log.amount = config.fx_rates.convert(
+                    transaction.amount,
+                    currency,
+                    base_currency,
+                    transaction.timestamp,
+                    &config.money,
+                )?;
+                transaction_log.currency = Some(base_currency);
+                transaction_log.original_amount = Some(transaction.amount);
+                transaction_log.original_currency = Some(currency);
+            }
+            (None, base_currency) => transaction_log.currency = base_currency,
+            _ => {}
+        }
+
+        Ok(transaction_log)
+    }
+
+    // Disputes act on the wallet of the transaction they refer to, and may
+    // only name its currency, or the one it was converted from
+    fn disputed_wallet(
+        &self,
+        transaction: &Transaction,
+        transaction_log: &TransactionLog,
+    ) -> Result<Wallet, TransactionError> {
+        match transaction.currency {
+            Some(currency)
+                if transaction_log.currency != Some(currency)
+                    && transaction_log.original_currency != Some(currency) =>
+            {
+                Err(TransactionError::CurrencyMismatch)
             }
+            _ => Ok(self.wallet(transaction_log.currency)),
+        }
+    }
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn shortfall(available: Amount, held: Amount) -> Amount {
+    match available.checked_add(held) {
+        Some(total) => (-total).max(Amount::ZERO),
+        None if available < Amount::ZERO => Amount::MAX,
+        None => Amount::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod test_wallet {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut account = Account::new();
+        let eur = Currency::parse("EUR");
+
+        assert!(account.wallet(eur) == Wallet::new(eur));
+
+        for (currency, available) in [
+            (eur, amount!(1.0)),
+            (None, amount!(2.0)),
+            (eur, amount!(3.0)),
+        ] {
+            account.set_wallet(Wallet {
+                available,
+                ..Wallet::new(currency)
+            });
         }
+
+        assert!(account.wallets.len() == 2);
+        assert!(account.wallets[0].currency.is_none());
+        assert!(account.wallet(None).available == amount!(2.0));
+        assert!(account.wallet(eur).available == amount!(3.0));
+    }
+}
+
+#[cfg(test)]
+mod test_balances {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert!(Account::new().balances() == vec![Wallet::new(None)]);
+    }
+
+    #[test]
+    fn ok() {
+        let mut account = Account::new();
+        let wallet = Wallet {
+            available: amount!(1.0),
+            ..Wallet::new(Currency::parse("USD"))
+        };
+
+        account.set_wallet(wallet);
+
+        assert!(account.balances() == vec![wallet]);
     }
 }
 
 #[cfg(test)]
 mod test_deposit {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
     fn transaction_already_exists() {
@@ -93,163 +488,2773 @@ mod test_deposit {
         //
 
         let mut account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let result = account.deposit(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::DuplicateTransaction));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn overflow_saturate() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: Amount::MAX,
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Deposit,
+            amount: amount!(7.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
         let mut transaction_logs = TransactionLogs::new();
+        let mut warnings = Warnings::new();
 
         //
         // action
         //
 
-        transaction_logs.add(&Transaction {
+        let result = account.deposit(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut warnings,
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: Amount::MAX,
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(
+            warnings
+                == vec![Warning::Saturated {
+                    transaction_id: 1,
+                    client_id: 1,
+                }]
+        );
+
+        assert!(transaction_logs.contains(1) == Ok(true));
+    }
+
+    #[test]
+    fn overflow_reject() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: Amount::MAX,
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
-        });
+            kind: TransactionKind::Deposit,
+            amount: amount!(7.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let config = Config {
+            overflow_policy: OverflowPolicy::Reject,
+            ..Config::new()
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+        let mut warnings = Warnings::new();
 
-        account.deposit(&transaction, &mut transaction_logs);
+        //
+        // action
+        //
+
+        let result = account.deposit(&transaction, &mut transaction_logs, &config, &mut warnings);
 
         //
         // test what we expect
         //
 
+        assert!(result == Err(TransactionError::Overflow));
+
         assert!(
             account
                 == Account {
-                    available: dec!(5.0),
-                    held: dec!(10.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: Amount::MAX,
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
-        let mut expected_transaction_logs = TransactionLogs::new();
+        assert!(warnings.is_empty());
+        assert!(transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
 
-        expected_transaction_logs.add(&Transaction {
+        let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
-        });
+            kind: TransactionKind::Deposit,
+            amount: amount!(7.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let result = account.deposit(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(12.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(7.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
 
         assert!(transaction_logs == expected_transaction_logs);
     }
 
     #[test]
-    fn ok() {
+    fn repays_debt() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(-5.0),
+                held: amount!(0.0),
+                debt: amount!(5.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        let deposit = |id, amount| Transaction {
+            id,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount,
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        //
+        // action
+        //
+
+        let first = account.deposit(
+            &deposit(1, amount!(3.0)),
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        let debt = account.wallet(None).debt;
+
+        let second = account.deposit(
+            &deposit(2, amount!(3.0)),
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(first == Ok(()));
+        assert!(second == Ok(()));
+        assert!(debt == amount!(2.0));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(1.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_deposit_currency {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(2.0),
+            currency: Currency::parse("EUR"),
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let result = account.deposit(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![
+                        Wallet {
+                            currency: None,
+                            available: amount!(5.0),
+                            held: amount!(0.0),
+                            debt: amount!(0.0),
+                        },
+                        Wallet {
+                            currency: Currency::parse("EUR"),
+                            available: amount!(2.0),
+                            held: amount!(0.0),
+                            debt: amount!(0.0),
+                        }
+                    ],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(
+            transaction_logs.get(1).unwrap().map(|t| t.currency) == Some(Currency::parse("EUR"))
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_withdrawal {
+    use super::*;
+
+    #[test]
+    fn transaction_already_exists() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let result = account.withdrawal(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::DuplicateTransaction));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn insufficient_funds() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(5.0001),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let result = account.withdrawal(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::InsufficientFunds));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn credit_limit() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(10.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(15.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let result = account.withdrawal(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(-10.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(10.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(transaction_logs.len() == 1);
+    }
+
+    #[test]
+    fn credit_limit_exceeded() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(10.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(15.0001),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let result = account.withdrawal(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::InsufficientFunds));
+        assert!(account.wallet(None).available == amount!(5.0));
+        assert!(transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(3.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let result = account.withdrawal(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+}
+
+#[cfg(test)]
+mod test_dispute {
+    use super::*;
+
+    #[test]
+    fn transaction_does_not_exist() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        //
+        // action
+        //
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::TransactionNotFound));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn transaction_exists_but_not_owned() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::TransactionNotOwned));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn already_disputed() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        //
+        // action
+        //
+
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::AlreadyDisputed));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn already_charged_back() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::ChargedBack) == Ok(()));
+
+        //
+        // action
+        //
+
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::AlreadyChargedBack));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::ChargedBack) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn redispute_not_allowed() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Resolved) == Ok(()));
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                allow_redispute: false,
+                ..DisputePolicy::new()
+            },
+            ..Config::new()
+        };
+
+        //
+        // action
+        //
+
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::RedisputeNotAllowed));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Resolved) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn overflow_reject() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: Amount::MAX,
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let config = Config {
+            overflow_policy: OverflowPolicy::Reject,
+            ..Config::new()
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        //
+        // action
+        //
+
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::Overflow));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: Amount::MAX,
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(
+            transaction_logs.get(1).unwrap().map(|t| t.state) == Some(TransactionState::Processed)
+        );
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        //
+        // action
+        //
+
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+}
+
+#[cfg(test)]
+mod test_dispute_currency {
+    use super::*;
+
+    fn setup() -> (Account, TransactionLogs) {
+        let account = Account {
+            wallets: vec![
+                Wallet {
+                    currency: None,
+                    available: amount!(5.0),
+                    held: amount!(0.0),
+                    debt: amount!(0.0),
+                },
+                Wallet {
+                    currency: Currency::parse("GBP"),
+                    available: amount!(3.0),
+                    held: amount!(0.0),
+                    debt: amount!(0.0),
+                },
+            ],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: Currency::parse("GBP"),
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        (account, transaction_logs)
+    }
+
+    fn dispute(currency: Option<Currency>) -> Transaction {
+        Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency,
+            timestamp: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn mismatch() {
+        let (mut account, mut transaction_logs) = setup();
+        let expected_account = account.clone();
+
+        let result = account.dispute(
+            &dispute(Currency::parse("EUR")),
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        assert!(result == Err(TransactionError::CurrencyMismatch));
+        assert!(account == expected_account);
+    }
+
+    #[test]
+    fn ok() {
+        for currency in [None, Currency::parse("GBP")] {
+            //
+            // setup
+            //
+
+            let (mut account, mut transaction_logs) = setup();
+
+            //
+            // action
+            //
+
+            let result = account.dispute(
+                &dispute(currency),
+                &mut transaction_logs,
+                &Config::default(),
+                &mut Warnings::new(),
+            );
+
+            //
+            // test what we expect
+            //
+
+            // The dispute holds funds in the currency of the deposit
+            assert!(result == Ok(()));
+            assert!(account.wallet(None).available == amount!(5.0));
+            assert!(account.wallet(Currency::parse("GBP")).available == amount!(0.0));
+            assert!(account.wallet(Currency::parse("GBP")).held == amount!(3.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_conversion {
+    use super::*;
+
+    fn usd() -> Option<Currency> {
+        Currency::parse("USD")
+    }
+
+    fn eur() -> Option<Currency> {
+        Currency::parse("EUR")
+    }
+
+    fn config() -> Config {
+        let mut config = Config::new();
+
+        for (timestamp, rate) in [(100, amount!(1.1)), (200, amount!(1.2))] {
+            config.fx_rates.add(FxRate {
+                timestamp,
+                from: eur().unwrap(),
+                to: usd().unwrap(),
+                rate,
+            });
+        }
+
+        config
+    }
+
+    fn account() -> Account {
+        Account {
+            wallets: vec![Wallet {
+                currency: usd(),
+                available: amount!(5.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: usd(),
+        }
+    }
+
+    fn transaction(
+        id: TransactionId,
+        kind: TransactionKind,
+        amount: Amount,
+        currency: Option<Currency>,
+        timestamp: Option<Timestamp>,
+    ) -> Transaction {
+        Transaction {
+            id,
+            client_id: 1,
+            kind,
+            amount,
+            currency,
+            timestamp,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn deposit() {
+        //
+        // setup
+        //
+
+        let mut account = account();
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let result = account.deposit(
+            &transaction(1, TransactionKind::Deposit, amount!(10.0), eur(), Some(150)),
+            &mut transaction_logs,
+            &config(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        // Converted at the rate in force at the time, into the base wallet
+        assert!(result == Ok(()));
+        assert!(account.wallets.len() == 1);
+        assert!(account.wallet(usd()).available == amount!(16.0));
+
+        assert!(
+            transaction_logs.get(1).unwrap()
+                == Some(TransactionLog {
+                    client_id: 1,
+                    kind: TransactionKind::Deposit,
+                    amount: amount!(11.0),
+                    currency: usd(),
+                    original_amount: Some(amount!(10.0)),
+                    original_currency: eur(),
+                    state: TransactionState::Processed,
+                })
+        );
+    }
+
+    #[test]
+    fn base_currency() {
+        for currency in [None, usd()] {
+            let mut account = account();
+            let mut transaction_logs = TransactionLogs::new();
+
+            let result = account.deposit(
+                &transaction(1, TransactionKind::Deposit, amount!(1.0), currency, None),
+                &mut transaction_logs,
+                &config(),
+                &mut Warnings::new(),
+            );
+
+            assert!(result == Ok(()));
+            assert!(account.wallet(usd()).available == amount!(6.0));
+            assert!(transaction_logs.get(1).unwrap().map(|t| t.currency) == Some(usd()));
+            assert!(transaction_logs.get(1).unwrap().map(|t| t.original_amount) == Some(None));
+        }
+    }
+
+    #[test]
+    fn rate_not_found() {
+        let mut account = account();
+        let mut transaction_logs = TransactionLogs::new();
+
+        for (currency, timestamp) in [(eur(), Some(99)), (Currency::parse("GBP"), None)] {
+            let result = account.deposit(
+                &transaction(
+                    1,
+                    TransactionKind::Deposit,
+                    amount!(1.0),
+                    currency,
+                    timestamp,
+                ),
+                &mut transaction_logs,
+                &config(),
+                &mut Warnings::new(),
+            );
+
+            assert!(result == Err(TransactionError::RateNotFound));
+        }
+
+        assert!(account == self::account());
+        assert!(transaction_logs.get(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn withdrawal() {
+        //
+        // setup
+        //
+
+        let mut account = account();
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        let refused = account.withdrawal(
+            &transaction(1, TransactionKind::Withdrawal, amount!(4.2), eur(), None),
+            &mut transaction_logs,
+            &config(),
+            &mut Warnings::new(),
+        );
+
+        let result = account.withdrawal(
+            &transaction(2, TransactionKind::Withdrawal, amount!(4.0), eur(), None),
+            &mut transaction_logs,
+            &config(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        // 4.2 EUR is 5.04 USD at the latest rate, more than is available
+        assert!(refused == Err(TransactionError::InsufficientFunds));
+        assert!(result == Ok(()));
+        assert!(account.wallet(usd()).available == amount!(0.2));
+    }
+
+    #[test]
+    fn dispute() {
+        for currency in [None, eur(), usd()] {
+            //
+            // setup
+            //
+
+            let mut account = account();
+            let mut transaction_logs = TransactionLogs::new();
+            let config = config();
+
+            assert!(
+                account.deposit(
+                    &transaction(1, TransactionKind::Deposit, amount!(10.0), eur(), Some(100)),
+                    &mut transaction_logs,
+                    &config,
+                    &mut Warnings::new(),
+                ) == Ok(())
+            );
+
+            //
+            // action
+            //
+
+            // Disputed later, when the rate has moved on
+            let result = account.dispute(
+                &transaction(
+                    1,
+                    TransactionKind::Dispute,
+                    amount!(0.0),
+                    currency,
+                    Some(300),
+                ),
+                &mut transaction_logs,
+                &config,
+                &mut Warnings::new(),
+            );
+
+            //
+            // test what we expect
+            //
+
+            // The amount held is what was credited at the time
+            assert!(result == Ok(()));
+            assert!(account.wallet(usd()).available == amount!(5.0));
+            assert!(account.wallet(usd()).held == amount!(11.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_resolve {
+    use super::*;
+
+    #[test]
+    fn transaction_does_not_exist() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        //
+        // action
+        //
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        let result = account.resolve(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::TransactionNotFound));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn transaction_exists_but_not_owned() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        let result = account.resolve(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::TransactionNotOwned));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn dispute_does_not_exist() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        //
+        // action
+        //
+
+        let result = account.resolve(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::DisputeNotFound));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        //
+        // action
+        //
+
+        let result = account.resolve(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Resolved) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+}
+
+#[cfg(test)]
+mod test_chargeback {
+    use super::*;
+
+    #[test]
+    fn transaction_does_not_exist() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        //
+        // action
+        //
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::TransactionNotFound));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        assert!(transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn transaction_exists_but_not_owned() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        //
+        // action
+        //
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::TransactionNotOwned));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn dispute_does_not_exist() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        //
+        // action
+        //
+
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::DisputeNotFound));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        //
+        // action
+        //
+
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::ChargedBack) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+    }
+
+    #[test]
+    fn debt() {
+        //
+        // setup
+        //
+
+        // 5.0 was deposited, 4.0 withdrawn and then the deposit disputed
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(-4.0),
+                held: amount!(5.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(5.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        //
+        // action
+        //
+
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            account
+                == Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(-4.0),
+                        held: amount!(0.0),
+                        debt: amount!(4.0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                }
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_dispute_withdrawal {
+    use super::*;
+
+    #[test]
+    fn refused() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(7.0),
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
         let mut transaction_logs = TransactionLogs::new();
 
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                deposit: DisputeMode::HoldFunds,
+                withdrawal: DisputeMode::Refuse,
+                allow_redispute: true,
+                window: None,
+            },
+            ..Config::new()
+        };
+
         //
         // action
         //
 
-        account.deposit(&transaction, &mut transaction_logs);
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Err(TransactionError::DisputeNotAllowed));
+
         assert!(
             account
                 == Account {
-                    available: dec!(12.0),
-                    held: dec!(10.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
         let mut expected_transaction_logs = TransactionLogs::new();
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(7.0),
-        });
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
 
         assert!(transaction_logs == expected_transaction_logs);
     }
-}
-
-#[cfg(test)]
-mod test_withdrawal {
-    use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
-    fn transaction_already_exists() {
+    fn hold_funds() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
         let mut transaction_logs = TransactionLogs::new();
 
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                deposit: DisputeMode::HoldFunds,
+                withdrawal: DisputeMode::HoldFunds,
+                allow_redispute: true,
+                window: None,
+            },
+            ..Config::new()
+        };
+
         //
         // action
         //
 
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(0.0),
-        });
-
-        account.withdrawal(&transaction, &mut transaction_logs);
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Ok(()));
+
         assert!(
             account
                 == Account {
-                    available: dec!(5.0),
-                    held: dec!(10.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
         let mut expected_transaction_logs = TransactionLogs::new();
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(0.0),
-        });
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
 
         assert!(transaction_logs == expected_transaction_logs);
     }
@@ -261,163 +3266,303 @@ mod test_withdrawal {
         //
 
         let mut account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(3.0),
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
         let mut transaction_logs = TransactionLogs::new();
 
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
         //
         // action
         //
 
-        account.withdrawal(&transaction, &mut transaction_logs);
+        let result = account.dispute(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Ok(()));
+
         assert!(
             account
                 == Account {
-                    available: dec!(2.0),
-                    held: dec!(10.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
         let mut expected_transaction_logs = TransactionLogs::new();
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
 
         assert!(transaction_logs == expected_transaction_logs);
     }
 }
 
 #[cfg(test)]
-mod test_dispute {
+mod test_resolve_withdrawal {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
-    fn transaction_does_not_exist() {
+    fn refused() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(2);
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                deposit: DisputeMode::HoldFunds,
+                withdrawal: DisputeMode::Refuse,
+                allow_redispute: true,
+                window: None,
+            },
+            ..Config::new()
+        };
 
         //
         // action
         //
 
-        let mut transaction_logs = TransactionLogs::new();
-
-        account.dispute(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.resolve(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Err(TransactionError::DisputeNotAllowed));
+
         assert!(
             account
                 == Account {
-                    available: dec!(5.0),
-                    held: dec!(10.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
 
-        assert!(transaction_logs == TransactionLogs::new());
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
     }
 
     #[test]
-    fn transaction_exists_but_not_owned() {
+    fn hold_funds() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(2);
-
         let mut transaction_logs = TransactionLogs::new();
 
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                deposit: DisputeMode::HoldFunds,
+                withdrawal: DisputeMode::HoldFunds,
+                allow_redispute: true,
+                window: None,
+            },
+            ..Config::new()
+        };
+
         //
         // action
         //
 
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 2,
-            amount: dec!(1.0),
-        });
-
-        account.dispute(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.resolve(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Ok(()));
+
         assert!(
             account
                 == Account {
-                    available: dec!(5.0),
-                    held: dec!(10.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
         let mut expected_transaction_logs = TransactionLogs::new();
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 2,
-            amount: dec!(1.0),
-        });
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Resolved) == Ok(()));
 
         assert!(transaction_logs == expected_transaction_logs);
     }
@@ -429,560 +3574,661 @@ mod test_dispute {
         //
 
         let mut account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(2);
-
         let mut transaction_logs = TransactionLogs::new();
 
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
 
         //
         // action
         //
 
-        account.dispute(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.resolve(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Ok(()));
+
         assert!(
             account
                 == Account {
-                    available: dec!(2.0),
-                    held: dec!(13.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(1);
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
         let mut expected_transaction_logs = TransactionLogs::new();
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Resolved) == Ok(()));
 
         assert!(transaction_logs == expected_transaction_logs);
     }
 }
 
 #[cfg(test)]
-mod test_resolve {
+mod test_chargeback_withdrawal {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
-    fn transaction_does_not_exist() {
+    fn refused() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(2);
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                deposit: DisputeMode::HoldFunds,
+                withdrawal: DisputeMode::Refuse,
+                allow_redispute: true,
+                window: None,
+            },
+            ..Config::new()
+        };
 
         //
         // action
         //
 
-        let mut transaction_logs = TransactionLogs::new();
-
-        account.resolve(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Err(TransactionError::DisputeNotAllowed));
+
         assert!(
             account
                 == Account {
-                    available: dec!(2.0),
-                    held: dec!(13.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(13.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
+        let mut expected_transaction_logs = TransactionLogs::new();
 
-        assert!(transaction_logs == TransactionLogs::new());
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
     }
 
     #[test]
-    fn transaction_exists_but_not_owned() {
+    fn hold_funds() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(2.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(1);
-        disputes.insert(2);
-
         let mut transaction_logs = TransactionLogs::new();
 
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                deposit: DisputeMode::HoldFunds,
+                withdrawal: DisputeMode::HoldFunds,
+                allow_redispute: true,
+                window: None,
+            },
+            ..Config::new()
+        };
+
         //
         // action
         //
 
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 2,
-            amount: dec!(1.0),
-        });
-
-        account.resolve(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &config,
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Ok(()));
+
         assert!(
             account
                 == Account {
-                    available: dec!(2.0),
-                    held: dec!(13.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(2.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(1);
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
         let mut expected_transaction_logs = TransactionLogs::new();
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 2,
-            amount: dec!(1.0),
-        });
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::ChargedBack) == Ok(()));
 
         assert!(transaction_logs == expected_transaction_logs);
     }
 
     #[test]
-    fn dispute_does_not_exist() {
+    fn ok() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(13.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(1);
-        disputes.insert(2);
-
         let mut transaction_logs = TransactionLogs::new();
 
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
 
         //
         // action
         //
 
-        disputes.remove(&1);
-
-        account.resolve(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.chargeback(
+            &transaction,
+            &mut transaction_logs,
+            &Config::default(),
+            &mut Warnings::new(),
+        );
 
         //
         // test what we expect
         //
 
+        assert!(result == Ok(()));
+
         assert!(
             account
                 == Account {
-                    available: dec!(2.0),
-                    held: dec!(13.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(8.0),
+                        held: amount!(10.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
 
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
         let mut expected_transaction_logs = TransactionLogs::new();
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
+        assert!(
+            expected_transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Withdrawal,
+                amount: amount!(3.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(expected_transaction_logs.set_state(1, TransactionState::ChargedBack) == Ok(()));
 
         assert!(transaction_logs == expected_transaction_logs);
     }
+}
+
+#[cfg(test)]
+mod test_change_status {
+    use super::*;
 
     #[test]
-    fn ok() {
+    fn invalid() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Unlock,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("reviewed")),
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(1);
-        disputes.insert(2);
-
-        let mut transaction_logs = TransactionLogs::new();
-
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
-
         //
         // action
         //
 
-        account.resolve(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.change_status(&transaction);
 
         //
         // test what we expect
         //
 
-        assert!(
-            account
-                == Account {
-                    available: dec!(5.0),
-                    held: dec!(10.0),
-                    locked: false,
-                }
-        );
-
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
-        let mut expected_transaction_logs = TransactionLogs::new();
-
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
-
-        assert!(transaction_logs == expected_transaction_logs);
+        assert!(result == Err(TransactionError::InvalidStatusChange));
+        assert!(account.status == AccountStatus::Active);
     }
-}
-
-#[cfg(test)]
-mod test_chargeback {
-    use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
-    fn transaction_does_not_exist() {
+    fn ok() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Locked,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Unlock,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("reviewed")),
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(2);
-
         //
         // action
         //
 
-        let mut transaction_logs = TransactionLogs::new();
-
-        account.chargeback(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.change_status(&transaction);
 
         //
         // test what we expect
         //
 
+        assert!(result == Ok(()));
+
         assert!(
             account
                 == Account {
-                    available: dec!(2.0),
-                    held: dec!(13.0),
-                    locked: false,
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
                 }
         );
-
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
-        assert!(transaction_logs == TransactionLogs::new());
     }
 
     #[test]
-    fn transaction_exists_but_not_owned() {
+    fn close_with_balance() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
-            id: 1,
+            id: 0,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Close,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("customer request")),
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(1);
-        disputes.insert(2);
-
-        let mut transaction_logs = TransactionLogs::new();
-
         //
         // action
         //
 
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 2,
-            amount: dec!(1.0),
-        });
-
-        account.chargeback(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.change_status(&transaction);
 
         //
         // test what we expect
         //
 
-        assert!(
-            account
-                == Account {
-                    available: dec!(2.0),
-                    held: dec!(13.0),
-                    locked: false,
-                }
-        );
-
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(1);
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
-        let mut expected_transaction_logs = TransactionLogs::new();
-
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 2,
-            amount: dec!(1.0),
-        });
-
-        assert!(transaction_logs == expected_transaction_logs);
+        assert!(result == Err(TransactionError::BalanceNotZero));
+        assert!(account.status == AccountStatus::Active);
     }
 
     #[test]
-    fn dispute_does_not_exist() {
+    fn close_with_debt() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(0.0),
+                held: amount!(0.0),
+                debt: amount!(2.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
-            id: 1,
+            id: 0,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Close,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("customer request")),
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(1);
-        disputes.insert(2);
-
-        let mut transaction_logs = TransactionLogs::new();
-
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
-
         //
         // action
         //
 
-        disputes.remove(&1);
-
-        account.chargeback(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.change_status(&transaction);
 
         //
         // test what we expect
         //
 
-        assert!(
-            account
-                == Account {
-                    available: dec!(2.0),
-                    held: dec!(13.0),
-                    locked: false,
-                }
-        );
-
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
-
-        let mut expected_transaction_logs = TransactionLogs::new();
-
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
-
-        assert!(transaction_logs == expected_transaction_logs);
+        assert!(result == Err(TransactionError::BalanceNotZero));
+        assert!(account.status == AccountStatus::Active);
     }
 
     #[test]
-    fn ok() {
+    fn close_empty() {
         //
         // setup
         //
 
         let mut account = Account {
-            available: dec!(2.0),
-            held: dec!(13.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(0.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let transaction = Transaction {
-            id: 1,
+            id: 0,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Close,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("customer request")),
         };
 
-        let mut disputes = HashSet::new();
-        disputes.insert(1);
-        disputes.insert(2);
-
-        let mut transaction_logs = TransactionLogs::new();
-
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
-
         //
         // action
         //
 
-        account.chargeback(&transaction, &mut transaction_logs, &mut disputes);
+        let result = account.change_status(&transaction);
 
         //
         // test what we expect
         //
 
-        assert!(
-            account
-                == Account {
-                    available: dec!(2.0),
-                    held: dec!(10.0),
-                    locked: true,
-                }
-        );
+        assert!(result == Ok(()));
+        assert!(account.status == AccountStatus::Closed);
+    }
+}
 
-        let mut expected_disputes = HashSet::new();
-        expected_disputes.insert(2);
-        assert!(disputes == expected_disputes);
+#[cfg(test)]
+mod test_is_locked {
+    use super::*;
 
-        let mut expected_transaction_logs = TransactionLogs::new();
+    #[test]
+    fn ok() {
+        let mut account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(0.0),
+                held: amount!(0.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
 
-        expected_transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 1,
-            amount: dec!(3.0),
-        });
+        assert!(!account.is_locked());
 
-        assert!(transaction_logs == expected_transaction_logs);
+        for status in [
+            AccountStatus::Frozen,
+            AccountStatus::Locked,
+            AccountStatus::Closed,
+        ] {
+            account.status = status;
+            assert!(account.is_locked());
+        }
     }
 }
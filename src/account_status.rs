@@ -0,0 +1,207 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountStatus {
+    Active,
+    Frozen,
+    Locked,
+    Closed,
+}
+
+impl AccountStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Frozen => "frozen",
+            AccountStatus::Locked => "locked",
+            AccountStatus::Closed => "closed",
+        }
+    }
+
+    pub fn permits(
+        &self,
+        kind: TransactionKind,
+        status_policy: &StatusPolicy,
+    ) -> Result<(), TransactionError> {
+        if kind.is_admin() {
+            return self.transition(kind).map(|_| ());
+        }
+
+        if status_policy.allows(*self, kind) {
+            return Ok(());
+        }
+
+        match self {
+            AccountStatus::Active => Ok(()),
+            AccountStatus::Frozen => Err(TransactionError::AccountFrozen),
+            AccountStatus::Locked => Err(TransactionError::AccountLocked),
+            AccountStatus::Closed => Err(TransactionError::AccountClosed),
+        }
+    }
+
+    pub fn transition(&self, kind: TransactionKind) -> Result<AccountStatus, TransactionError> {
+        match (self, kind) {
+            (AccountStatus::Active, TransactionKind::Freeze) => Ok(AccountStatus::Frozen),
+            (AccountStatus::Frozen, TransactionKind::Unfreeze) => Ok(AccountStatus::Active),
+            (AccountStatus::Locked, TransactionKind::Unlock) => Ok(AccountStatus::Active),
+            (AccountStatus::Closed, _) => Err(TransactionError::AccountClosed),
+            (_, TransactionKind::Close) => Ok(AccountStatus::Closed),
+            _ => Err(TransactionError::InvalidStatusChange),
+        }
+    }
+}
+
+impl fmt::Display for AccountStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test_permits {
+    use super::*;
+
+    #[test]
+    fn active() {
+        let status_policy = StatusPolicy::new();
+        let status = AccountStatus::Active;
+
+        assert!(status.permits(TransactionKind::Deposit, &status_policy) == Ok(()));
+        assert!(status.permits(TransactionKind::Withdrawal, &status_policy) == Ok(()));
+        assert!(status.permits(TransactionKind::Freeze, &status_policy) == Ok(()));
+        assert!(
+            status.permits(TransactionKind::Unlock, &status_policy)
+                == Err(TransactionError::InvalidStatusChange)
+        );
+    }
+
+    #[test]
+    fn frozen() {
+        let status_policy = StatusPolicy::new();
+        let status = AccountStatus::Frozen;
+
+        assert!(
+            status.permits(TransactionKind::Deposit, &status_policy)
+                == Err(TransactionError::AccountFrozen)
+        );
+        assert!(status.permits(TransactionKind::Resolve, &status_policy) == Ok(()));
+        assert!(status.permits(TransactionKind::Unfreeze, &status_policy) == Ok(()));
+    }
+
+    #[test]
+    fn locked() {
+        let status_policy = StatusPolicy::new();
+        let status = AccountStatus::Locked;
+
+        assert!(
+            status.permits(TransactionKind::Withdrawal, &status_policy)
+                == Err(TransactionError::AccountLocked)
+        );
+        assert!(
+            status.permits(TransactionKind::Dispute, &status_policy)
+                == Err(TransactionError::AccountLocked)
+        );
+        assert!(status.permits(TransactionKind::Chargeback, &status_policy) == Ok(()));
+        assert!(status.permits(TransactionKind::Unlock, &status_policy) == Ok(()));
+    }
+
+    #[test]
+    fn locked_custom() {
+        let status_policy = StatusPolicy {
+            locked: vec![TransactionKind::Deposit],
+            ..StatusPolicy::new()
+        };
+
+        let status = AccountStatus::Locked;
+
+        assert!(status.permits(TransactionKind::Deposit, &status_policy) == Ok(()));
+        assert!(
+            status.permits(TransactionKind::Resolve, &status_policy)
+                == Err(TransactionError::AccountLocked)
+        );
+    }
+
+    #[test]
+    fn closed() {
+        let status_policy = StatusPolicy::new();
+        let status = AccountStatus::Closed;
+
+        assert!(
+            status.permits(TransactionKind::Resolve, &status_policy)
+                == Err(TransactionError::AccountClosed)
+        );
+        assert!(
+            status.permits(TransactionKind::Unlock, &status_policy)
+                == Err(TransactionError::AccountClosed)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_transition {
+    use super::*;
+
+    #[test]
+    fn active() {
+        let status = AccountStatus::Active;
+
+        assert!(status.transition(TransactionKind::Freeze) == Ok(AccountStatus::Frozen));
+        assert!(status.transition(TransactionKind::Close) == Ok(AccountStatus::Closed));
+        assert!(
+            status.transition(TransactionKind::Unfreeze)
+                == Err(TransactionError::InvalidStatusChange)
+        );
+        assert!(
+            status.transition(TransactionKind::Unlock)
+                == Err(TransactionError::InvalidStatusChange)
+        );
+    }
+
+    #[test]
+    fn frozen() {
+        let status = AccountStatus::Frozen;
+
+        assert!(status.transition(TransactionKind::Unfreeze) == Ok(AccountStatus::Active));
+        assert!(status.transition(TransactionKind::Close) == Ok(AccountStatus::Closed));
+        assert!(
+            status.transition(TransactionKind::Freeze)
+                == Err(TransactionError::InvalidStatusChange)
+        );
+        assert!(
+            status.transition(TransactionKind::Unlock)
+                == Err(TransactionError::InvalidStatusChange)
+        );
+    }
+
+    #[test]
+    fn locked() {
+        let status = AccountStatus::Locked;
+
+        assert!(status.transition(TransactionKind::Unlock) == Ok(AccountStatus::Active));
+        assert!(status.transition(TransactionKind::Close) == Ok(AccountStatus::Closed));
+        assert!(
+            status.transition(TransactionKind::Freeze)
+                == Err(TransactionError::InvalidStatusChange)
+        );
+        assert!(
+            status.transition(TransactionKind::Unfreeze)
+                == Err(TransactionError::InvalidStatusChange)
+        );
+    }
+
+    #[test]
+    fn closed() {
+        for kind in [
+            TransactionKind::Freeze,
+            TransactionKind::Unfreeze,
+            TransactionKind::Unlock,
+            TransactionKind::Close,
+        ] {
+            assert!(AccountStatus::Closed.transition(kind) == Err(TransactionError::AccountClosed));
+        }
+    }
+}
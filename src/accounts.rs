@@ -1,10 +1,10 @@
 use super::*;
 
-use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Accounts(HashMap<ClientId, Account>);
 
 impl Accounts {
@@ -12,29 +12,8 @@ impl Accounts {
         Accounts(HashMap::new())
     }
 
-    pub fn find_or_create(&mut self, client_id: ClientId) -> Option<Account> {
-        match self.get(&client_id) {
-            Some(account) => {
-                if account.locked {
-                    // Assuming we don't want to act on frozen accounts
-                    return None;
-                }
-
-                Some(*account)
-            }
-            None => {
-                self.insert(
-                    client_id,
-                    Account {
-                        available: Decimal::ZERO,
-                        held: Decimal::ZERO,
-                        locked: false,
-                    },
-                );
-
-                self.get(&client_id).copied()
-            }
-        }
+    pub fn find_or_create(&mut self, client_id: ClientId) -> Account {
+        self.entry(client_id).or_default().clone()
     }
 }
 
@@ -78,20 +57,21 @@ mod test_new {
 #[cfg(test)]
 mod test_find_or_create {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
     fn new() {
         let mut accounts = Accounts::new();
         let account = accounts.find_or_create(1);
 
+        // Wallets are only opened by the transactions made in them
         let expected_account = Account {
-            available: dec!(0.0),
-            held: dec!(0.0),
-            locked: false,
+            wallets: vec![],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
-        assert!(account == Some(expected_account));
+        assert!(account == expected_account);
     }
 
     #[test]
@@ -101,15 +81,33 @@ mod test_find_or_create {
         accounts.insert(
             1,
             Account {
-                available: dec!(5.0),
-                held: dec!(10.0),
-                locked: true,
+                wallets: vec![Wallet {
+                    currency: None,
+                    available: amount!(5.0),
+                    held: amount!(10.0),
+                    debt: amount!(0.0),
+                }],
+                status: AccountStatus::Locked,
+                credit_limit: amount!(0.0),
+                base_currency: None,
             },
         );
 
+        let expected_account = Account {
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Locked,
+            credit_limit: amount!(0.0),
+            base_currency: None,
+        };
+
         let account = accounts.find_or_create(1);
 
-        assert!(account == None);
+        assert!(account == expected_account);
     }
 
     #[test]
@@ -119,28 +117,39 @@ mod test_find_or_create {
         accounts.insert(
             1,
             Account {
-                available: dec!(5.0),
-                held: dec!(10.0),
-                locked: false,
+                wallets: vec![Wallet {
+                    currency: None,
+                    available: amount!(5.0),
+                    held: amount!(10.0),
+                    debt: amount!(0.0),
+                }],
+                status: AccountStatus::Active,
+                credit_limit: amount!(0.0),
+                base_currency: None,
             },
         );
 
         let expected_account = Account {
-            available: dec!(5.0),
-            held: dec!(10.0),
-            locked: false,
+            wallets: vec![Wallet {
+                currency: None,
+                available: amount!(5.0),
+                held: amount!(10.0),
+                debt: amount!(0.0),
+            }],
+            status: AccountStatus::Active,
+            credit_limit: amount!(0.0),
+            base_currency: None,
         };
 
         let account = accounts.find_or_create(1);
 
-        assert!(account == Some(expected_account));
+        assert!(account == expected_account);
     }
 }
 
 #[cfg(test)]
 mod test_derefs {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
     fn derefed_ok() {
@@ -155,9 +164,15 @@ mod test_derefs {
         accounts.insert(
             1,
             Account {
-                available: dec!(5.0),
-                held: dec!(10.0),
-                locked: false,
+                wallets: vec![Wallet {
+                    currency: None,
+                    available: amount!(5.0),
+                    held: amount!(10.0),
+                    debt: amount!(0.0),
+                }],
+                status: AccountStatus::Active,
+                credit_limit: amount!(0.0),
+                base_currency: None,
             },
         );
 
@@ -0,0 +1,368 @@
+use super::*;
+
+use serde::Serialize;
+use std::io::{self, Write};
+
+// One row of the balances, with amounts formatted the same for every format
+#[derive(Serialize)]
+pub struct Balance {
+    pub client: ClientId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+    pub locked: bool,
+    // What chargebacks left uncovered, last so the CSV columns before it
+    // are unchanged
+    pub debt: String,
+}
+
+impl Balance {
+    pub fn new(
+        client_id: ClientId,
+        account: &Account,
+        wallet: &Wallet,
+        money: &Money,
+        warnings: &mut Warnings,
+    ) -> Self {
+        // Clamped like any other balance, but never silently
+        let total = match wallet.available.checked_add(wallet.held) {
+            Some(total) => total,
+            None => {
+                warnings.push(Warning::SaturatedTotal { client_id });
+                wallet.available.saturating_add(wallet.held)
+            }
+        };
+
+        Balance {
+            client: client_id,
+            currency: wallet.currency,
+            available: money.format(wallet.available),
+            held: money.format(wallet.held),
+            total: money.format(total),
+            locked: account.is_locked(),
+            debt: money.format(wallet.debt),
+        }
+    }
+}
+
+pub trait BalanceWriter {
+    fn write(&mut self, balance: &Balance) -> io::Result<()>;
+
+    // Ends the output and flushes it
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+pub struct CsvBalances<W: Write> {
+    writer: W,
+    currencies: bool,
+}
+
+impl<W: Write> CsvBalances<W> {
+    // The currency column is only written when some balance has a currency,
+    // so single currency output is unchanged
+    pub fn new(mut writer: W, currencies: bool) -> io::Result<Self> {
+        if currencies {
+            writeln!(writer, "client,currency,available,held,total,locked,debt")?;
+        } else {
+            writeln!(writer, "client,available,held,total,locked,debt")?;
+        }
+
+        Ok(CsvBalances { writer, currencies })
+    }
+}
+
+impl<W: Write> BalanceWriter for CsvBalances<W> {
+    fn write(&mut self, balance: &Balance) -> io::Result<()> {
+        write!(self.writer, "{},", balance.client)?;
+
+        if self.currencies {
+            match balance.currency {
+                Some(currency) => write!(self.writer, "{currency},")?,
+                None => write!(self.writer, ",")?,
+            }
+        }
+
+        writeln!(
+            self.writer,
+            "{},{},{},{},{}",
+            balance.available, balance.held, balance.total, balance.locked, balance.debt
+        )
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct JsonBalances<W: Write> {
+    writer: W,
+    empty: bool,
+}
+
+impl<W: Write> JsonBalances<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        write!(writer, "[")?;
+
+        Ok(JsonBalances {
+            writer,
+            empty: true,
+        })
+    }
+}
+
+impl<W: Write> BalanceWriter for JsonBalances<W> {
+    fn write(&mut self, balance: &Balance) -> io::Result<()> {
+        if !self.empty {
+            write!(self.writer, ",")?;
+        }
+
+        self.empty = false;
+
+        Ok(serde_json::to_writer(&mut self.writer, balance)?)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        writeln!(self.writer, "]")?;
+
+        self.writer.flush()
+    }
+}
+
+pub struct JsonLinesBalances<W: Write>(W);
+
+impl<W: Write> JsonLinesBalances<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesBalances(writer)
+    }
+}
+
+impl<W: Write> BalanceWriter for JsonLinesBalances<W> {
+    fn write(&mut self, balance: &Balance) -> io::Result<()> {
+        serde_json::to_writer(&mut self.0, balance)?;
+
+        writeln!(self.0)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod test_new {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut accounts = Accounts::new();
+
+        let account = accounts.find_or_create(1);
+
+        let wallet = Wallet {
+            available: amount!(1.23456),
+            held: amount!(2.0),
+            ..Wallet::new(Currency::parse("EUR"))
+        };
+
+        let mut warnings = Warnings::new();
+        let balance = Balance::new(1, &account, &wallet, &Money::new(), &mut warnings);
+
+        assert!(warnings.is_empty());
+        assert!(balance.client == 1);
+        assert!(balance.currency == Currency::parse("EUR"));
+        assert!(balance.available == "1.2345");
+        assert!(balance.held == "2");
+        assert!(balance.total == "3.2345");
+        assert!(!balance.locked);
+        assert!(balance.debt == "0");
+    }
+
+    #[test]
+    fn chargeback() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        for (id, kind, amount) in [
+            (1, TransactionKind::Deposit, amount!(10.0)),
+            (2, TransactionKind::Withdrawal, amount!(8.0)),
+            (1, TransactionKind::Dispute, amount!(0.0)),
+            (1, TransactionKind::Chargeback, amount!(0.0)),
+        ] {
+            assert!(
+                ledger.apply(Transaction {
+                    id,
+                    client_id: 1,
+                    kind,
+                    amount,
+                    currency: None,
+                    timestamp: None,
+                    reason: None,
+                }) == Ok(())
+            );
+        }
+
+        let account = ledger.accounts.get(&1).unwrap();
+
+        //
+        // action
+        //
+
+        let balance = Balance::new(
+            1,
+            account,
+            &account.wallet(None),
+            &Money::new(),
+            &mut Warnings::new(),
+        );
+
+        //
+        // test what we expect
+        //
+
+        assert!(balance.available == "-8");
+        assert!(balance.held == "0");
+        assert!(balance.total == "-8");
+        assert!(balance.locked);
+        assert!(balance.debt == "8");
+    }
+
+    #[test]
+    fn saturated_total() {
+        let mut accounts = Accounts::new();
+
+        let account = accounts.find_or_create(1);
+
+        let wallet = Wallet {
+            available: Amount::MAX,
+            held: amount!(2.0),
+            ..Wallet::new(None)
+        };
+
+        let mut warnings = Warnings::new();
+        let balance = Balance::new(1, &account, &wallet, &Money::new(), &mut warnings);
+
+        assert!(balance.total == Money::new().format(Amount::MAX));
+        assert!(warnings == vec![Warning::SaturatedTotal { client_id: 1 }]);
+    }
+}
+
+#[cfg(test)]
+mod test_write {
+    use super::*;
+
+    fn balances() -> Vec<Balance> {
+        [
+            (1, "1.5", "0", "1.5", false, "0"),
+            (2, "-1", "2", "1", true, "1"),
+        ]
+        .into_iter()
+        .map(|(client, available, held, total, locked, debt)| Balance {
+            client,
+            currency: None,
+            available: String::from(available),
+            held: String::from(held),
+            total: String::from(total),
+            locked,
+            debt: String::from(debt),
+        })
+        .collect()
+    }
+
+    fn write<B: BalanceWriter>(mut writer: B, balances: &[Balance]) -> B {
+        for balance in balances {
+            assert!(writer.write(balance).is_ok());
+        }
+
+        assert!(writer.finish().is_ok());
+
+        writer
+    }
+
+    #[test]
+    fn csv() {
+        let writer = write(CsvBalances::new(Vec::new(), false).unwrap(), &balances());
+
+        assert!(
+            writer.writer
+                == b"client,available,held,total,locked,debt\n\
+                     1,1.5,0,1.5,false,0\n\
+                     2,-1,2,1,true,1\n"
+        );
+    }
+
+    #[test]
+    fn csv_currencies() {
+        let mut balances = balances();
+
+        balances[0].currency = Currency::parse("EUR");
+
+        let writer = write(CsvBalances::new(Vec::new(), true).unwrap(), &balances);
+
+        assert!(
+            writer.writer
+                == b"client,currency,available,held,total,locked,debt\n\
+                     1,EUR,1.5,0,1.5,false,0\n\
+                     2,,-1,2,1,true,1\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let writer = write(JsonBalances::new(Vec::new()).unwrap(), &balances());
+
+        assert!(
+            String::from_utf8(writer.writer).unwrap()
+                == concat!(
+                    r#"[{"client":1,"available":"1.5","held":"0","total":"1.5","locked":false,"debt":"0"},"#,
+                    r#"{"client":2,"available":"-1","held":"2","total":"1","locked":true,"debt":"1"}]"#,
+                    "\n"
+                )
+        );
+    }
+
+    #[test]
+    fn json_currencies() {
+        let mut balances = balances();
+
+        balances[1].currency = Currency::parse("GBP");
+
+        let writer = write(JsonBalances::new(Vec::new()).unwrap(), &balances);
+
+        assert!(
+            String::from_utf8(writer.writer).unwrap()
+                == concat!(
+                    r#"[{"client":1,"available":"1.5","held":"0","total":"1.5","locked":false,"debt":"0"},"#,
+                    r#"{"client":2,"currency":"GBP","available":"-1","held":"2","total":"1","#,
+                    r#""locked":true,"debt":"1"}]"#,
+                    "\n"
+                )
+        );
+    }
+
+    #[test]
+    fn json_empty() {
+        let writer = write(JsonBalances::new(Vec::new()).unwrap(), &[]);
+
+        assert!(writer.writer == b"[]\n");
+    }
+
+    #[test]
+    fn json_lines() {
+        let writer = write(JsonLinesBalances::new(Vec::new()), &balances());
+
+        assert!(
+            String::from_utf8(writer.0).unwrap()
+                == concat!(
+                    r#"{"client":1,"available":"1.5","held":"0","total":"1.5","locked":false,"debt":"0"}"#,
+                    "\n",
+                    r#"{"client":2,"available":"-1","held":"2","total":"1","locked":true,"debt":"1"}"#,
+                    "\n"
+                )
+        );
+    }
+}
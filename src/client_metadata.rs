@@ -0,0 +1,103 @@
+use super::*;
+
+use csv::StringRecord;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClientMetadata {
+    pub client_id: ClientId,
+    pub credit_limit: Amount,
+    pub base_currency: Option<Currency>,
+}
+
+impl ClientMetadata {
+    pub fn parse_record(record: &StringRecord) -> Result<ClientMetadata, TransactionError> {
+        if record.len() < 2 {
+            return Err(TransactionError::MissingColumns);
+        }
+
+        Ok(ClientMetadata {
+            client_id: match String::from(&record[0]).parse() {
+                Ok(client_id) => client_id,
+                _ => return Err(TransactionError::InvalidClientId),
+            },
+            credit_limit: match Amount::from_str(&record[1]) {
+                Ok(credit_limit) if credit_limit >= Amount::ZERO => credit_limit,
+                _ => return Err(TransactionError::InvalidAmount),
+            },
+            base_currency: match record.get(2) {
+                None | Some("") => None,
+                Some(code) => match Currency::parse(code) {
+                    Some(currency) => Some(currency),
+                    None => return Err(TransactionError::InvalidCurrency),
+                },
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_parse_record {
+    use super::*;
+
+    #[test]
+    fn one_col() {
+        let record = StringRecord::from(vec!["1"]);
+        let client_metadata = ClientMetadata::parse_record(&record);
+
+        assert!(client_metadata == Err(TransactionError::MissingColumns));
+    }
+
+    #[test]
+    fn invalid_client_id() {
+        let record = StringRecord::from(vec!["-1", "100.0"]);
+        let client_metadata = ClientMetadata::parse_record(&record);
+
+        assert!(client_metadata == Err(TransactionError::InvalidClientId));
+    }
+
+    #[test]
+    fn negative_credit_limit() {
+        let record = StringRecord::from(vec!["1", "-100.0"]);
+        let client_metadata = ClientMetadata::parse_record(&record);
+
+        assert!(client_metadata == Err(TransactionError::InvalidAmount));
+    }
+
+    #[test]
+    fn ok() {
+        let record = StringRecord::from(vec!["1", "100.0"]);
+        let client_metadata = ClientMetadata::parse_record(&record);
+
+        assert!(
+            client_metadata
+                == Ok(ClientMetadata {
+                    client_id: 1,
+                    credit_limit: amount!(100.0),
+                    base_currency: None,
+                })
+        );
+    }
+
+    #[test]
+    fn invalid_base_currency() {
+        let record = StringRecord::from(vec!["1", "100.0", "EURO"]);
+        let client_metadata = ClientMetadata::parse_record(&record);
+
+        assert!(client_metadata == Err(TransactionError::InvalidCurrency));
+    }
+
+    #[test]
+    fn base_currency() {
+        let record = StringRecord::from(vec!["1", "0", "usd"]);
+        let client_metadata = ClientMetadata::parse_record(&record);
+
+        assert!(
+            client_metadata
+                == Ok(ClientMetadata {
+                    client_id: 1,
+                    credit_limit: amount!(0),
+                    base_currency: Currency::parse("USD"),
+                })
+        );
+    }
+}
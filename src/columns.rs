@@ -0,0 +1,386 @@
+use super::*;
+
+use csv::StringRecord;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+// The names of the type, client, tx, amount, currency and timestamp columns,
+// in canonical order
+const NAMES: [&str; 6] = ["type", "client", "tx", "amount", "currency", "timestamp"];
+
+// Maps the records of an input onto the canonical
+// type,client,tx,amount,currency,timestamp layout read by
+// Transaction::parse_record
+#[derive(Clone, Debug, PartialEq)]
+pub enum Columns {
+    // Already in canonical order, with any reason in the amount column. Fields
+    // past the header's (or the amount, if the header is shorter) are ignored.
+    Positional {
+        width: usize,
+    },
+    // Found by name in the header, in any order
+    Named {
+        kind: usize,
+        client_id: usize,
+        transaction_id: usize,
+        amount: Option<usize>,
+        reason: Option<usize>,
+        currency: Option<usize>,
+        timestamp: Option<usize>,
+    },
+    // A JSON object per record, keyed by the same names
+    Json,
+}
+
+impl Columns {
+    // Headers without the type, client and tx names are taken as positional
+    pub fn from_header(header: &StringRecord) -> Columns {
+        let position = |name: &str| {
+            header
+                .iter()
+                .position(|column| column.eq_ignore_ascii_case(name))
+        };
+
+        let (kind, client_id, transaction_id) =
+            match (position(NAMES[0]), position(NAMES[1]), position(NAMES[2])) {
+                (Some(kind), Some(client_id), Some(transaction_id)) => {
+                    (kind, client_id, transaction_id)
+                }
+                _ => return Columns::positional(header),
+            };
+
+        let amount = position(NAMES[3]);
+        let currency = position(NAMES[4]);
+        let timestamp = position(NAMES[5]);
+        let reason = position("reason");
+
+        let in_order = (kind, client_id, transaction_id) == (0, 1, 2);
+
+        // Any other column in their place would be taken for them
+        let optional_in_order =
+            [amount, currency, timestamp]
+                .iter()
+                .zip(3..)
+                .all(|(column, index)| match column {
+                    Some(column) => *column == index,
+                    None => header.len() <= index,
+                });
+
+        if in_order && optional_in_order && reason.is_none() {
+            return Columns::positional(header);
+        }
+
+        Columns::Named {
+            kind,
+            client_id,
+            transaction_id,
+            amount,
+            reason,
+            currency,
+            timestamp,
+        }
+    }
+
+    fn positional(header: &StringRecord) -> Columns {
+        Columns::Positional {
+            width: header.len().max(4),
+        }
+    }
+
+    // A canonical copy of the record, which is left as it was read for the
+    // rejects report. Positional records are only copied if they need cutting.
+    pub fn normalize<'a>(
+        &self,
+        record: &'a StringRecord,
+    ) -> Result<Cow<'a, StringRecord>, TransactionError> {
+        let fields = match self {
+            Columns::Positional { width } if record.len() <= *width => {
+                return Ok(Cow::Borrowed(record))
+            }
+            Columns::Positional { width } => {
+                let mut fields = record.clone();
+                fields.truncate(*width);
+                fields
+            }
+            Columns::Named {
+                kind,
+                client_id,
+                transaction_id,
+                amount,
+                reason,
+                currency,
+                timestamp,
+            } => {
+                let field = |index: &Option<usize>| index.and_then(|index| record.get(index));
+
+                canonical([
+                    record.get(*kind),
+                    record.get(*client_id),
+                    record.get(*transaction_id),
+                    amount_or_reason(field(amount), field(reason)),
+                    field(currency),
+                    field(timestamp),
+                ])
+            }
+            Columns::Json => {
+                let object = match serde_json::from_str::<Value>(record.get(0).unwrap_or_default())
+                {
+                    Ok(Value::Object(object)) => object,
+                    _ => return Err(TransactionError::MalformedRecord),
+                };
+
+                let values = NAMES
+                    .iter()
+                    .chain(["reason"].iter())
+                    .map(|name| text(&object, name))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                canonical([
+                    values[0].as_deref(),
+                    values[1].as_deref(),
+                    values[2].as_deref(),
+                    amount_or_reason(values[3].as_deref(), values[6].as_deref()),
+                    values[4].as_deref(),
+                    values[5].as_deref(),
+                ])
+            }
+        };
+
+        Ok(Cow::Owned(fields))
+    }
+}
+
+// Admin rows carry their reason where the amount would be
+fn amount_or_reason<'a>(amount: Option<&'a str>, reason: Option<&'a str>) -> Option<&'a str> {
+    match amount {
+        Some(amount) if !amount.is_empty() => Some(amount),
+        _ => reason.or(amount),
+    }
+}
+
+// Missing trailing fields are left out, so they are reported as missing
+fn canonical(fields: [Option<&str>; 6]) -> StringRecord {
+    let len = fields
+        .iter()
+        .rposition(|field| field.is_some())
+        .map(|last| last + 1)
+        .unwrap_or_default();
+
+    fields[..len]
+        .iter()
+        .map(|field| field.unwrap_or_default())
+        .collect()
+}
+
+fn text(object: &Map<String, Value>, name: &str) -> Result<Option<String>, TransactionError> {
+    match object.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text.trim().to_string())),
+        // Numbers keep their exact text, so amounts lose no precision
+        Some(Value::Number(number)) => Ok(Some(number.to_string())),
+        Some(_) => Err(TransactionError::MalformedRecord),
+    }
+}
+
+#[cfg(test)]
+mod test_from_header {
+    use super::*;
+
+    #[test]
+    fn positional() {
+        for (header, width) in [
+            (vec!["type", "client", "tx", "amount"], 4),
+            (vec!["type", "client", "tx"], 4),
+            (vec!["type", "client", "tx", "amount", "currency"], 5),
+            (
+                vec!["type", "client", "tx", "amount", "currency", "timestamp"],
+                6,
+            ),
+            (vec!["kind", "customer", "id", "value"], 4),
+        ] {
+            let columns = Columns::from_header(&StringRecord::from(header));
+
+            assert!(columns == Columns::Positional { width });
+        }
+    }
+
+    #[test]
+    fn named() {
+        let header = StringRecord::from(vec!["Amount", "tx", "note", "client", "TYPE", "reason"]);
+
+        assert!(
+            Columns::from_header(&header)
+                == Columns::Named {
+                    kind: 4,
+                    client_id: 3,
+                    transaction_id: 1,
+                    amount: Some(0),
+                    reason: Some(5),
+                    currency: None,
+                    timestamp: None,
+                }
+        );
+    }
+
+    #[test]
+    fn extra_column() {
+        let header = StringRecord::from(vec!["type", "client", "tx", "amount", "note"]);
+
+        assert!(
+            Columns::from_header(&header)
+                == Columns::Named {
+                    kind: 0,
+                    client_id: 1,
+                    transaction_id: 2,
+                    amount: Some(3),
+                    reason: None,
+                    currency: None,
+                    timestamp: None,
+                }
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_normalize {
+    use super::*;
+
+    fn normalize(columns: &Columns, fields: Vec<&str>) -> Result<StringRecord, TransactionError> {
+        let record = StringRecord::from(fields);
+
+        columns.normalize(&record).map(Cow::into_owned)
+    }
+
+    #[test]
+    fn positional() {
+        let record = normalize(
+            &Columns::Positional { width: 5 },
+            vec!["deposit", "1", "2", "1.0", "EUR"],
+        );
+
+        assert!(record == Ok(StringRecord::from(vec!["deposit", "1", "2", "1.0", "EUR"])));
+    }
+
+    #[test]
+    fn positional_extra_fields() {
+        let columns =
+            Columns::from_header(&StringRecord::from(vec!["type", "client", "tx", "amount"]));
+
+        let deposit = normalize(&columns, vec!["deposit", "1", "1", "1.0", "foo"]);
+        let dispute = normalize(&columns, vec!["dispute", "1", "1"]);
+
+        assert!(deposit == Ok(StringRecord::from(vec!["deposit", "1", "1", "1.0"])));
+        assert!(dispute == Ok(StringRecord::from(vec!["dispute", "1", "1"])));
+    }
+
+    #[test]
+    fn named() {
+        let columns = Columns::from_header(&StringRecord::from(vec![
+            "amount", "tx", "note", "client", "type", "reason",
+        ]));
+
+        let deposit = normalize(&columns, vec!["1.0", "2", "hello", "1", "deposit", ""]);
+        let freeze = normalize(&columns, vec!["", "0", "", "1", "freeze", "fraud"]);
+        let short = normalize(&columns, vec!["", "2", "", "1"]);
+
+        assert!(deposit == Ok(StringRecord::from(vec!["deposit", "1", "2", "1.0"])));
+        assert!(freeze == Ok(StringRecord::from(vec!["freeze", "1", "0", "fraud"])));
+        assert!(short == Ok(StringRecord::from(vec!["", "1", "2", ""])));
+    }
+
+    #[test]
+    fn named_currency() {
+        let columns = Columns::from_header(&StringRecord::from(vec![
+            "currency", "type", "client", "tx", "amount",
+        ]));
+
+        let deposit = normalize(&columns, vec!["gbp", "deposit", "1", "2", "1.0"]);
+        let dispute = normalize(&columns, vec!["GBP", "dispute", "1", "2", ""]);
+
+        assert!(deposit == Ok(StringRecord::from(vec!["deposit", "1", "2", "1.0", "gbp"])));
+        assert!(dispute == Ok(StringRecord::from(vec!["dispute", "1", "2", "", "GBP"])));
+    }
+
+    #[test]
+    fn named_timestamp() {
+        let columns = Columns::from_header(&StringRecord::from(vec![
+            "timestamp",
+            "type",
+            "client",
+            "tx",
+            "amount",
+        ]));
+
+        let deposit = normalize(&columns, vec!["1700000000", "deposit", "1", "2", "1.0"]);
+
+        assert!(
+            deposit
+                == Ok(StringRecord::from(vec![
+                    "deposit",
+                    "1",
+                    "2",
+                    "1.0",
+                    "",
+                    "1700000000"
+                ]))
+        );
+    }
+
+    #[test]
+    fn json() {
+        let deposit = normalize(
+            &Columns::Json,
+            vec![r#"{"tx": 2, "amount": 1.00000000000000000001, "client": 1, "type": "deposit"}"#],
+        );
+        let dispute = normalize(
+            &Columns::Json,
+            vec![r#"{"type": "dispute", "client": 1, "tx": 2}"#],
+        );
+        let freeze = normalize(
+            &Columns::Json,
+            vec![r#"{"type": "freeze", "client": 1, "tx": 0, "reason": "fraud"}"#],
+        );
+        let withdrawal = normalize(
+            &Columns::Json,
+            vec![
+                r#"{"type": "withdrawal", "client": 1, "tx": 3, "amount": "2", "currency": "USD", "timestamp": 1700000000}"#,
+            ],
+        );
+
+        assert!(
+            deposit
+                == Ok(StringRecord::from(vec![
+                    "deposit",
+                    "1",
+                    "2",
+                    "1.00000000000000000001"
+                ]))
+        );
+        assert!(dispute == Ok(StringRecord::from(vec!["dispute", "1", "2"])));
+        assert!(freeze == Ok(StringRecord::from(vec!["freeze", "1", "0", "fraud"])));
+        assert!(
+            withdrawal
+                == Ok(StringRecord::from(vec![
+                    "withdrawal",
+                    "1",
+                    "3",
+                    "2",
+                    "USD",
+                    "1700000000"
+                ]))
+        );
+    }
+
+    #[test]
+    fn json_malformed() {
+        for line in [
+            r#"{"type": "deposit""#,
+            r#"["deposit", 1, 2]"#,
+            r#"{"type": ["deposit"]}"#,
+        ] {
+            assert!(
+                normalize(&Columns::Json, vec![line]) == Err(TransactionError::MalformedRecord)
+            );
+        }
+    }
+}
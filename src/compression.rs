@@ -0,0 +1,234 @@
+use bzip2::read::MultiBzDecoder;
+use bzip2::write::BzEncoder;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, Read, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    pub fn parse(compression: &str) -> Option<Compression> {
+        match compression {
+            "none" => Some(Compression::None),
+            "gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            "bzip2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    // By the magic bytes the data starts with, then by the file extension, so
+    // that a damaged .gz fails to decompress rather than being read as CSV
+    pub fn detect(magic: &[u8], filename: &str) -> Compression {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if magic.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if filename.ends_with(".gz") {
+            Compression::Gzip
+        } else if filename.ends_with(".zst") {
+            Compression::Zstd
+        } else if filename.ends_with(".bz2") {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+
+    // Concatenated streams (from pigz or pbzip2, say) are read through
+    pub fn decoder<'a, R: Read + 'a>(&self, reader: R) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::None => Box::new(reader),
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+        })
+    }
+
+    pub fn encoder<W: Write>(&self, writer: W) -> io::Result<Encoder<W>> {
+        Ok(match self {
+            Compression::None => Encoder::None(writer),
+            Compression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(writer, 0)?),
+            Compression::Bzip2 => {
+                Encoder::Bzip2(BzEncoder::new(writer, bzip2::Compression::default()))
+            }
+        })
+    }
+}
+
+pub enum Encoder<W: Write> {
+    None(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Bzip2(BzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    // Writes the end of the stream, which dropping the encoder can't report
+    // errors from
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::None(writer) => Ok(writer),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+            Encoder::Bzip2(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::None(writer) => writer.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::None(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+            Encoder::Bzip2(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(Compression::parse("lz4").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(Compression::parse("none") == Some(Compression::None));
+        assert!(Compression::parse("gzip") == Some(Compression::Gzip));
+        assert!(Compression::parse("zstd") == Some(Compression::Zstd));
+        assert!(Compression::parse("bzip2") == Some(Compression::Bzip2));
+    }
+}
+
+#[cfg(test)]
+mod test_detect {
+    use super::*;
+
+    #[test]
+    fn magic() {
+        assert!(Compression::detect(&[0x1f, 0x8b, 0x08, 0x00], "input.csv") == Compression::Gzip);
+        assert!(Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd], "input.csv") == Compression::Zstd);
+        assert!(Compression::detect(b"BZh9", "input.csv") == Compression::Bzip2);
+        assert!(Compression::detect(b"BZh9", "input.csv.gz") == Compression::Bzip2);
+    }
+
+    #[test]
+    fn extension() {
+        assert!(Compression::detect(b"type", "input.csv.gz") == Compression::Gzip);
+        assert!(Compression::detect(b"type", "input.csv.zst") == Compression::Zstd);
+        assert!(Compression::detect(b"", "input.csv.bz2") == Compression::Bzip2);
+    }
+
+    #[test]
+    fn none() {
+        assert!(Compression::detect(b"type", "input.csv") == Compression::None);
+        assert!(Compression::detect(b"", "-") == Compression::None);
+    }
+}
+
+#[cfg(test)]
+mod test_decoder {
+    use super::*;
+
+    const INPUT: &str = "type,client,tx,amount\ndeposit,1,1,1.0\n";
+
+    #[test]
+    fn round_trip() {
+        for compression in [
+            Compression::None,
+            Compression::Gzip,
+            Compression::Zstd,
+            Compression::Bzip2,
+        ] {
+            //
+            // setup
+            //
+
+            let mut encoder = compression.encoder(Vec::new()).unwrap();
+
+            encoder.write_all(INPUT.as_bytes()).unwrap();
+
+            let compressed = encoder.finish().unwrap();
+
+            //
+            // action
+            //
+
+            let detected = Compression::detect(&compressed[..4], "-");
+            let mut decompressed = String::new();
+
+            detected
+                .decoder(compressed.as_slice())
+                .unwrap()
+                .read_to_string(&mut decompressed)
+                .unwrap();
+
+            //
+            // test what we expect
+            //
+
+            assert!(detected == compression);
+            assert!(decompressed == INPUT);
+        }
+    }
+
+    #[test]
+    fn concatenated() {
+        let mut compressed = Vec::new();
+
+        for part in ["type,client,tx,amount\n", "deposit,1,1,1.0\n"] {
+            let mut encoder = Compression::Gzip.encoder(Vec::new()).unwrap();
+
+            encoder.write_all(part.as_bytes()).unwrap();
+            compressed.extend(encoder.finish().unwrap());
+        }
+
+        let mut decompressed = String::new();
+
+        Compression::Gzip
+            .decoder(compressed.as_slice())
+            .unwrap()
+            .read_to_string(&mut decompressed)
+            .unwrap();
+
+        assert!(decompressed == INPUT);
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut decompressed = String::new();
+
+        let result = Compression::Gzip
+            .decoder(INPUT.as_bytes())
+            .unwrap()
+            .read_to_string(&mut decompressed);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,51 @@
+use super::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub dispute_policy: DisputePolicy,
+    pub overflow_policy: OverflowPolicy,
+    pub status_policy: StatusPolicy,
+    pub storage: Storage,
+    pub fx_rates: FxRates,
+    pub money: Money,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config {
+            dispute_policy: DisputePolicy::new(),
+            overflow_policy: OverflowPolicy::Saturate,
+            status_policy: StatusPolicy::new(),
+            storage: Storage::Memory,
+            fx_rates: FxRates::new(),
+            money: Money::new(),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_new {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let config = Config::new();
+        assert!(config.dispute_policy == DisputePolicy::new());
+        assert!(config.overflow_policy == OverflowPolicy::Saturate);
+        assert!(config.status_policy == StatusPolicy::new());
+        assert!(config.storage == Storage::Memory);
+        assert!(config.fx_rates == FxRates::new());
+        assert!(config.money == Money::new());
+    }
+
+    #[test]
+    fn default() {
+        assert!(Config::default() == Config::new());
+    }
+}
@@ -0,0 +1,91 @@
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+// A three letter ISO 4217 code, such as EUR, held inline so logs stay Copy
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    // Codes are case-insensitive, and always held in upper case
+    pub fn parse(code: &str) -> Option<Currency> {
+        match code.as_bytes() {
+            [a, b, c] if code.bytes().all(|byte| byte.is_ascii_alphabetic()) => Some(Currency([
+                a.to_ascii_uppercase(),
+                b.to_ascii_uppercase(),
+                c.to_ascii_uppercase(),
+            ])),
+            _ => None,
+        }
+    }
+
+    pub fn from_bytes(bytes: [u8; 3]) -> Option<Currency> {
+        Currency::parse(std::str::from_utf8(&bytes).ok()?)
+    }
+
+    pub fn as_bytes(&self) -> [u8; 3] {
+        self.0
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only ever built from ASCII letters
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+
+        Currency::parse(&code)
+            .ok_or_else(|| de::Error::custom(format!("invalid currency '{code}'")))
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn invalid() {
+        assert!(Currency::parse("").is_none());
+        assert!(Currency::parse("EU").is_none());
+        assert!(Currency::parse("EURO").is_none());
+        assert!(Currency::parse("E1R").is_none());
+        assert!(Currency::parse("ÉUR").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(Currency::parse("EUR").map(|c| c.to_string()) == Some(String::from("EUR")));
+        assert!(Currency::parse("gbp").map(|c| c.to_string()) == Some(String::from("GBP")));
+        assert!(Currency::parse("Usd") == Currency::parse("USD"));
+    }
+}
+
+#[cfg(test)]
+mod test_serialize {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let currency = Currency::parse("eur").unwrap();
+        let json = serde_json::to_string(&currency).unwrap();
+
+        assert!(json == r#""EUR""#);
+        assert!(serde_json::from_str::<Currency>(&json).unwrap() == currency);
+        assert!(serde_json::from_str::<Currency>(r#""EURO""#).is_err());
+    }
+}
@@ -0,0 +1,511 @@
+use super::*;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// id, client, kind, state, the 16 bytes of the amount and the currency, then
+// the original amount and currency of a converted transaction, with zeros for
+// none
+const RECORD_SIZE: u64 = 46;
+
+// Each index slot holds the data offset of the latest record plus one
+const SLOT_SIZE: u64 = 8;
+
+// How many records are read at once when iterating
+const READ_RECORDS: usize = 4096;
+
+static STORES: AtomicUsize = AtomicUsize::new(0);
+
+struct DiskFiles {
+    data: File,
+    index: File,
+    data_len: u64,
+}
+
+// Logs are appended to a data file, and every update appends a new record
+// rather than rewriting the old one. The index file is addressed directly by
+// transaction id, so it is sparse and never needs to be held in memory.
+pub struct DiskStore {
+    data_path: PathBuf,
+    index_path: PathBuf,
+    files: Option<DiskFiles>,
+    len: usize,
+}
+
+impl DiskStore {
+    pub fn new(directory: &Path) -> Self {
+        // Stores are scratch space, so every one gets files of its own
+        let name = format!(
+            "transaction-logs-{}-{}",
+            process::id(),
+            STORES.fetch_add(1, Ordering::Relaxed)
+        );
+
+        DiskStore {
+            data_path: directory.join(format!("{name}.data")),
+            index_path: directory.join(format!("{name}.index")),
+            files: None,
+            len: 0,
+        }
+    }
+
+    fn files(&mut self) -> io::Result<&mut DiskFiles> {
+        if self.files.is_none() {
+            let open = |path: &Path| {
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(path)
+            };
+
+            self.files = Some(DiskFiles {
+                data: open(&self.data_path)?,
+                index: open(&self.index_path)?,
+                data_len: 0,
+            });
+        }
+
+        Ok(self.files.as_mut().unwrap())
+    }
+}
+
+impl DiskFiles {
+    fn slot(&self, transaction_id: TransactionId) -> io::Result<Option<u64>> {
+        let mut index = &self.index;
+        let mut slot = [0; SLOT_SIZE as usize];
+
+        index.seek(SeekFrom::Start(transaction_id as u64 * SLOT_SIZE))?;
+
+        match index.read_exact(&mut slot) {
+            Ok(()) => {}
+            // Slots past the end of the index were never written
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+
+        Ok(u64::from_le_bytes(slot).checked_sub(1))
+    }
+
+    fn record(&self, offset: u64) -> io::Result<(TransactionId, TransactionLog)> {
+        let mut data = &self.data;
+        let mut record = [0; RECORD_SIZE as usize];
+
+        data.seek(SeekFrom::Start(offset))?;
+        data.read_exact(&mut record)?;
+
+        decode(&record)
+    }
+}
+
+impl TransactionLogStore for DiskStore {
+    fn get(&self, transaction_id: TransactionId) -> io::Result<Option<TransactionLog>> {
+        let files = match &self.files {
+            Some(files) => files,
+            None => return Ok(None),
+        };
+
+        match files.slot(transaction_id)? {
+            Some(offset) => Ok(Some(files.record(offset)?.1)),
+            None => Ok(None),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        transaction_id: TransactionId,
+        transaction_log: TransactionLog,
+    ) -> io::Result<()> {
+        let files = self.files()?;
+        let offset = files.data_len;
+        let is_new = files.slot(transaction_id)?.is_none();
+
+        let mut data = &files.data;
+
+        data.seek(SeekFrom::Start(offset))?;
+        data.write_all(&encode(transaction_id, &transaction_log))?;
+
+        let mut index = &files.index;
+
+        index.seek(SeekFrom::Start(transaction_id as u64 * SLOT_SIZE))?;
+        index.write_all(&(offset + 1).to_le_bytes())?;
+
+        files.data_len += RECORD_SIZE;
+
+        if is_new {
+            self.len += 1;
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, transaction_id: TransactionId) -> io::Result<()> {
+        let files = match &self.files {
+            Some(files) => files,
+            None => return Ok(()),
+        };
+
+        if files.slot(transaction_id)?.is_some() {
+            // The record stays in the data file, it just can't be reached
+            let mut index = &files.index;
+
+            index.seek(SeekFrom::Start(transaction_id as u64 * SLOT_SIZE))?;
+            index.write_all(&0u64.to_le_bytes())?;
+
+            self.len -= 1;
+        }
+
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = StoreEntry> + '_> {
+        match &self.files {
+            Some(files) => Box::new(DiskIter {
+                files,
+                offset: 0,
+                records: Vec::new(),
+            }),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+impl Drop for DiskStore {
+    fn drop(&mut self) {
+        if self.files.take().is_some() {
+            let _ = fs::remove_file(&self.data_path);
+            let _ = fs::remove_file(&self.index_path);
+        }
+    }
+}
+
+struct DiskIter<'a> {
+    files: &'a DiskFiles,
+    offset: u64,
+    // Records read ahead, in reverse so the next one can be popped off
+    records: Vec<(u64, [u8; RECORD_SIZE as usize])>,
+}
+
+impl DiskIter<'_> {
+    fn fill(&mut self) -> io::Result<()> {
+        let remaining = (self.files.data_len - self.offset) / RECORD_SIZE;
+        let count = remaining.min(READ_RECORDS as u64) as usize;
+        let mut buffer = vec![0; count * RECORD_SIZE as usize];
+
+        let mut data = &self.files.data;
+
+        data.seek(SeekFrom::Start(self.offset))?;
+        data.read_exact(&mut buffer)?;
+
+        for (n, chunk) in buffer.chunks_exact(RECORD_SIZE as usize).enumerate().rev() {
+            let mut record = [0; RECORD_SIZE as usize];
+
+            record.copy_from_slice(chunk);
+            self.records
+                .push((self.offset + n as u64 * RECORD_SIZE, record));
+        }
+
+        self.offset += count as u64 * RECORD_SIZE;
+
+        Ok(())
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<(TransactionId, TransactionLog)>> {
+        loop {
+            if self.records.is_empty() {
+                if self.offset >= self.files.data_len {
+                    return Ok(None);
+                }
+
+                self.fill()?;
+            }
+
+            let (offset, record) = self.records.pop().unwrap();
+            let (transaction_id, transaction_log) = decode(&record)?;
+
+            // Only the latest record of each transaction counts
+            if self.files.slot(transaction_id)? == Some(offset) {
+                return Ok(Some((transaction_id, transaction_log)));
+            }
+        }
+    }
+}
+
+impl Iterator for DiskIter<'_> {
+    type Item = StoreEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_entry() {
+            Ok(entry) => entry.map(Ok),
+            Err(err) => {
+                // Don't keep failing on the same records
+                self.records.clear();
+                self.offset = self.files.data_len;
+
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn encode(
+    transaction_id: TransactionId,
+    transaction_log: &TransactionLog,
+) -> [u8; RECORD_SIZE as usize] {
+    let mut record = [0; RECORD_SIZE as usize];
+
+    record[0..4].copy_from_slice(&transaction_id.to_le_bytes());
+    record[4..6].copy_from_slice(&transaction_log.client_id.to_le_bytes());
+    record[6] = kind_code(transaction_log.kind);
+    record[7] = state_code(transaction_log.state);
+    record[8..24].copy_from_slice(&transaction_log.amount.serialize());
+
+    if let Some(currency) = transaction_log.currency {
+        record[24..27].copy_from_slice(&currency.as_bytes());
+    }
+
+    if let (Some(amount), Some(currency)) = (
+        transaction_log.original_amount,
+        transaction_log.original_currency,
+    ) {
+        record[27..43].copy_from_slice(&amount.serialize());
+        record[43..46].copy_from_slice(&currency.as_bytes());
+    }
+
+    record
+}
+
+fn decode(record: &[u8; RECORD_SIZE as usize]) -> io::Result<(TransactionId, TransactionLog)> {
+    let invalid = || io::Error::new(ErrorKind::InvalidData, "invalid transaction log record");
+
+    let amount = |bytes: &[u8]| {
+        let mut amount = [0; 16];
+
+        amount.copy_from_slice(bytes);
+        Amount::deserialize(amount)
+    };
+
+    let currency = |bytes: &[u8]| match [bytes[0], bytes[1], bytes[2]] {
+        [0, 0, 0] => Ok(None),
+        bytes => Currency::from_bytes(bytes).map(Some).ok_or_else(invalid),
+    };
+
+    let original_currency = currency(&record[43..46])?;
+
+    Ok((
+        TransactionId::from_le_bytes([record[0], record[1], record[2], record[3]]),
+        TransactionLog {
+            client_id: ClientId::from_le_bytes([record[4], record[5]]),
+            kind: kind_from_code(record[6]).ok_or_else(invalid)?,
+            state: state_from_code(record[7]).ok_or_else(invalid)?,
+            amount: amount(&record[8..24]),
+            currency: currency(&record[24..27])?,
+            original_amount: original_currency.map(|_| amount(&record[27..43])),
+            original_currency,
+        },
+    ))
+}
+
+const KINDS: [TransactionKind; 9] = [
+    TransactionKind::Deposit,
+    TransactionKind::Withdrawal,
+    TransactionKind::Dispute,
+    TransactionKind::Resolve,
+    TransactionKind::Chargeback,
+    TransactionKind::Freeze,
+    TransactionKind::Unfreeze,
+    TransactionKind::Unlock,
+    TransactionKind::Close,
+];
+
+const STATES: [TransactionState; 4] = [
+    TransactionState::Processed,
+    TransactionState::Disputed,
+    TransactionState::Resolved,
+    TransactionState::ChargedBack,
+];
+
+fn kind_code(kind: TransactionKind) -> u8 {
+    KINDS.iter().position(|k| *k == kind).unwrap_or_default() as u8
+}
+
+fn kind_from_code(code: u8) -> Option<TransactionKind> {
+    KINDS.get(code as usize).copied()
+}
+
+fn state_code(state: TransactionState) -> u8 {
+    STATES.iter().position(|s| *s == state).unwrap_or_default() as u8
+}
+
+fn state_from_code(code: u8) -> Option<TransactionState> {
+    STATES.get(code as usize).copied()
+}
+
+#[cfg(test)]
+mod test_insert {
+    use super::*;
+
+    fn transaction_log(amount: Amount, state: TransactionState) -> TransactionLog {
+        TransactionLog {
+            client_id: 7,
+            kind: TransactionKind::Withdrawal,
+            amount,
+            currency: Currency::parse("GBP"),
+            original_amount: None,
+            original_currency: None,
+            state,
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let store = DiskStore::new(&std::env::temp_dir());
+
+        assert!(store.len() == 0);
+        assert!(store.get(1).unwrap().is_none());
+        assert!(store.iter().next().is_none());
+        assert!(!store.data_path.exists());
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut store = DiskStore::new(&std::env::temp_dir());
+
+        let processed = transaction_log(amount!(1.2345), TransactionState::Processed);
+        let disputed = transaction_log(amount!(1.2345), TransactionState::Disputed);
+        let large = TransactionLog {
+            original_amount: Some(Amount::MIN),
+            original_currency: Currency::parse("JPY"),
+            ..transaction_log(Amount::MAX, TransactionState::ChargedBack)
+        };
+
+        //
+        // action
+        //
+
+        assert!(store.insert(3, processed).is_ok());
+        assert!(store.insert(u32::MAX, large).is_ok());
+        assert!(store.insert(3, disputed).is_ok());
+
+        //
+        // test what we expect
+        //
+
+        assert!(store.len() == 2);
+        assert!(store.get(3).unwrap() == Some(disputed));
+        assert!(store.get(u32::MAX).unwrap() == Some(large));
+        assert!(store.get(4).unwrap().is_none());
+
+        let mut entries = store.iter().map(|entry| entry.unwrap()).collect::<Vec<_>>();
+
+        entries.sort_by_key(|(id, _)| *id);
+
+        assert!(entries == vec![(3, disputed), (u32::MAX, large)]);
+    }
+
+    #[test]
+    fn drop_removes_files() {
+        let mut store = DiskStore::new(&std::env::temp_dir());
+
+        assert!(store
+            .insert(
+                1,
+                transaction_log(amount!(1.0), TransactionState::Processed)
+            )
+            .is_ok());
+
+        let paths = [store.data_path.clone(), store.index_path.clone()];
+
+        assert!(paths.iter().all(|path| path.exists()));
+
+        drop(store);
+
+        assert!(paths.iter().all(|path| !path.exists()));
+    }
+
+    #[test]
+    fn missing_directory() {
+        let mut store = DiskStore::new(Path::new("/nonexistent/directory"));
+
+        let result = store.insert(
+            1,
+            transaction_log(amount!(1.0), TransactionState::Processed),
+        );
+
+        assert!(result.is_err());
+        assert!(store.len() == 0);
+    }
+}
+
+#[cfg(test)]
+mod test_remove {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut store = DiskStore::new(&std::env::temp_dir());
+
+        let transaction_log = TransactionLog {
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            original_amount: None,
+            original_currency: None,
+            state: TransactionState::Processed,
+        };
+
+        assert!(store.remove(1).is_ok());
+        assert!(store.insert(1, transaction_log).is_ok());
+        assert!(store.insert(2, transaction_log).is_ok());
+        assert!(store.remove(1).is_ok());
+        assert!(store.remove(1).is_ok());
+
+        assert!(store.len() == 1);
+        assert!(store.get(1).unwrap().is_none());
+        assert!(
+            store.iter().map(|entry| entry.unwrap()).collect::<Vec<_>>()
+                == vec![(2, transaction_log)]
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_iter {
+    use super::*;
+
+    #[test]
+    fn many() {
+        let mut store = DiskStore::new(&std::env::temp_dir());
+        let count = READ_RECORDS as u32 * 2 + 10;
+
+        for id in 0..count {
+            let transaction_log = TransactionLog {
+                client_id: (id % 100) as ClientId,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                original_amount: None,
+                original_currency: None,
+                state: TransactionState::Processed,
+            };
+
+            assert!(store.insert(id, transaction_log).is_ok());
+        }
+
+        assert!(store.len() == count as usize);
+        assert!(store.iter().filter(|entry| entry.is_ok()).count() == count as usize);
+    }
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisputeMode {
+    // Funds are moved from available to held, and taken away on chargeback
+    HoldFunds,
+    // The amount is held as a pending credit, and credited on chargeback
+    PendingCredit,
+    // Disputes are refused outright
+    Refuse,
+}
+
+impl DisputeMode {
+    pub fn parse(mode: &str) -> Option<DisputeMode> {
+        match mode {
+            "hold-funds" => Some(DisputeMode::HoldFunds),
+            "pending-credit" => Some(DisputeMode::PendingCredit),
+            "refuse" => Some(DisputeMode::Refuse),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisputePolicy {
+    pub deposit: DisputeMode,
+    pub withdrawal: DisputeMode,
+    pub allow_redispute: bool,
+    // How many of the latest logged transactions may be disputed, if limited
+    pub window: Option<usize>,
+}
+
+impl DisputePolicy {
+    pub fn new() -> Self {
+        DisputePolicy {
+            deposit: DisputeMode::HoldFunds,
+            withdrawal: DisputeMode::PendingCredit,
+            allow_redispute: true,
+            window: None,
+        }
+    }
+
+    pub fn mode(&self, kind: TransactionKind) -> DisputeMode {
+        match kind {
+            TransactionKind::Deposit => self.deposit,
+            TransactionKind::Withdrawal => self.withdrawal,
+            _ => DisputeMode::Refuse,
+        }
+    }
+}
+
+impl Default for DisputePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(DisputeMode::parse("hold").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(DisputeMode::parse("hold-funds") == Some(DisputeMode::HoldFunds));
+        assert!(DisputeMode::parse("pending-credit") == Some(DisputeMode::PendingCredit));
+        assert!(DisputeMode::parse("refuse") == Some(DisputeMode::Refuse));
+    }
+}
+
+#[cfg(test)]
+mod test_mode {
+    use super::*;
+
+    #[test]
+    fn default() {
+        let dispute_policy = DisputePolicy::default();
+
+        assert!(dispute_policy.mode(TransactionKind::Deposit) == DisputeMode::HoldFunds);
+        assert!(dispute_policy.mode(TransactionKind::Withdrawal) == DisputeMode::PendingCredit);
+        assert!(dispute_policy.mode(TransactionKind::Dispute) == DisputeMode::Refuse);
+    }
+
+    #[test]
+    fn configured() {
+        let dispute_policy = DisputePolicy {
+            deposit: DisputeMode::Refuse,
+            withdrawal: DisputeMode::HoldFunds,
+            allow_redispute: true,
+            window: None,
+        };
+
+        assert!(dispute_policy.mode(TransactionKind::Deposit) == DisputeMode::Refuse);
+        assert!(dispute_policy.mode(TransactionKind::Withdrawal) == DisputeMode::HoldFunds);
+    }
+}
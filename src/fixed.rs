@@ -0,0 +1,448 @@
+use rust_decimal::prelude::*;
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::ops::Neg;
+
+// An amount held as a whole number of 10^-18 units, so that adding and
+// comparing amounts are plain integer operations. It offers the part of
+// Decimal's interface the ledger uses, so either can be the Amount.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i128);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParseFixedError;
+
+impl fmt::Display for ParseFixedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid amount")
+    }
+}
+
+impl std::error::Error for ParseFixedError {}
+
+const UNIT: i128 = 1_000_000_000_000_000_000;
+
+// Powers of ten up to UNIT, for rounding to fewer places
+const POWERS: [i128; 19] = {
+    let mut powers = [1; 19];
+    let mut n = 1;
+
+    while n < powers.len() {
+        powers[n] = powers[n - 1] * 10;
+        n += 1;
+    }
+
+    powers
+};
+
+impl Fixed {
+    // Decimal places held, which is also the most Money can be set to
+    pub const SCALE: u32 = 18;
+
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(UNIT);
+    // Symmetric, so every amount can be negated
+    pub const MAX: Fixed = Fixed(i128::MAX);
+    pub const MIN: Fixed = Fixed(-i128::MAX);
+
+    fn new(units: i128) -> Option<Fixed> {
+        (units != i128::MIN).then_some(Fixed(units))
+    }
+
+    pub fn checked_add(self, other: Fixed) -> Option<Fixed> {
+        Fixed::new(self.0.checked_add(other.0)?)
+    }
+
+    pub fn checked_sub(self, other: Fixed) -> Option<Fixed> {
+        Fixed::new(self.0.checked_sub(other.0)?)
+    }
+
+    pub fn saturating_add(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_add(other.0).max(Fixed::MIN.0))
+    }
+
+    pub fn saturating_sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_sub(other.0).max(Fixed::MIN.0))
+    }
+
+    // Truncated to the units held
+    pub fn checked_mul(self, other: Fixed) -> Option<Fixed> {
+        let (a, b) = (self.0.unsigned_abs(), other.0.unsigned_abs());
+        let unit = UNIT as u128;
+
+        // Split into whole and fractional parts, so no product overflows
+        // unless the result would
+        let (a_whole, a_fraction) = (a / unit, a % unit);
+        let (b_whole, b_fraction) = (b / unit, b % unit);
+
+        let product = a_whole
+            .checked_mul(b_whole)?
+            .checked_mul(unit)?
+            .checked_add(a_whole.checked_mul(b_fraction)?)?
+            .checked_add(a_fraction.checked_mul(b_whole)?)?
+            .checked_add(a_fraction * b_fraction / unit)?;
+
+        Fixed::signed(product, (self.0 < 0) != (other.0 < 0))
+    }
+
+    // Truncated to the units held
+    pub fn checked_div(self, other: Fixed) -> Option<Fixed> {
+        let (a, b) = (self.0.unsigned_abs(), other.0.unsigned_abs());
+
+        if b == 0 {
+            return None;
+        }
+
+        // Long division, a decimal place at a time
+        let mut quotient = (a / b).checked_mul(UNIT as u128)?;
+        let mut remainder = a % b;
+
+        for power in POWERS[..Fixed::SCALE as usize].iter().rev() {
+            remainder = remainder.checked_mul(10)?;
+            quotient = quotient.checked_add(remainder / b * *power as u128)?;
+            remainder %= b;
+        }
+
+        Fixed::signed(quotient, (self.0 < 0) != (other.0 < 0))
+    }
+
+    fn signed(magnitude: u128, negative: bool) -> Option<Fixed> {
+        let units = i128::try_from(magnitude).ok()?;
+
+        Some(Fixed(if negative { -units } else { units }))
+    }
+
+    pub fn round_dp_with_strategy(&self, dp: u32, strategy: RoundingStrategy) -> Fixed {
+        if dp >= Fixed::SCALE {
+            return *self;
+        }
+
+        let factor = POWERS[(Fixed::SCALE - dp) as usize];
+        let (quotient, remainder) = (self.0 / factor, self.0 % factor);
+
+        if remainder == 0 {
+            return *self;
+        }
+
+        let negative = self.0 < 0;
+        let half = (remainder.unsigned_abs() * 2).cmp(&(factor as u128));
+
+        #[allow(deprecated)]
+        let away_from_zero = match strategy {
+            RoundingStrategy::ToZero | RoundingStrategy::RoundDown => false,
+            RoundingStrategy::AwayFromZero | RoundingStrategy::RoundUp => true,
+            RoundingStrategy::ToNegativeInfinity => negative,
+            RoundingStrategy::ToPositiveInfinity => !negative,
+            RoundingStrategy::MidpointAwayFromZero | RoundingStrategy::RoundHalfUp => half.is_ge(),
+            RoundingStrategy::MidpointTowardZero | RoundingStrategy::RoundHalfDown => half.is_gt(),
+            RoundingStrategy::MidpointNearestEven | RoundingStrategy::BankersRounding => {
+                half.is_gt() || (half.is_eq() && quotient % 2 != 0)
+            }
+        };
+
+        let quotient = match (away_from_zero, negative) {
+            (false, _) => quotient,
+            (true, false) => quotient + 1,
+            (true, true) => quotient - 1,
+        };
+
+        // Rounding up at the very top of the range stays at the top
+        match quotient.checked_mul(factor).and_then(Fixed::new) {
+            Some(rounded) => rounded,
+            None if negative => Fixed::MIN,
+            None => Fixed::MAX,
+        }
+    }
+
+    // Every value has a single representation, unlike with Decimal
+    pub fn normalize(&self) -> Fixed {
+        *self
+    }
+
+    pub fn serialize(&self) -> [u8; 16] {
+        self.0.to_le_bytes()
+    }
+
+    pub fn deserialize(bytes: [u8; 16]) -> Fixed {
+        Fixed(i128::from_le_bytes(bytes)).max(Fixed::MIN)
+    }
+}
+
+// Reads the digits straight into the units, rather than going through a
+// Decimal. Places beyond those held are dropped.
+impl FromStr for Fixed {
+    type Err = ParseFixedError;
+
+    fn from_str(text: &str) -> Result<Fixed, ParseFixedError> {
+        let (negative, text) = match text.as_bytes() {
+            [b'-', text @ ..] => (true, text),
+            [b'+', text @ ..] => (false, text),
+            text => (false, text),
+        };
+
+        let (whole, fraction) = match text.iter().position(|byte| *byte == b'.') {
+            Some(point) => (&text[..point], &text[point + 1..]),
+            None => (text, &text[text.len()..]),
+        };
+
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(ParseFixedError);
+        }
+
+        let (fraction, dropped) = fraction.split_at(fraction.len().min(Fixed::SCALE as usize));
+
+        if !dropped.iter().all(u8::is_ascii_digit) {
+            return Err(ParseFixedError);
+        }
+
+        // At most 18 digits scaled up to 18 places, so well within a u64
+        let fraction = digits(fraction).ok_or(ParseFixedError)?
+            * POWERS[Fixed::SCALE as usize - fraction.len()] as u64;
+
+        let whole = match whole.len() {
+            // Can't overflow, so skip the costlier checked multiply
+            0..=19 => Some(digits(whole).ok_or(ParseFixedError)? as u128 * UNIT as u128),
+            _ => long_digits(whole).and_then(|whole| whole.checked_mul(UNIT as u128)),
+        };
+
+        whole
+            .and_then(|whole| whole.checked_add(fraction as u128))
+            .and_then(|units| Fixed::signed(units, negative))
+            .ok_or(ParseFixedError)
+    }
+}
+
+fn digit(byte: &u8) -> Option<u8> {
+    Some(byte.wrapping_sub(b'0')).filter(|digit| *digit < 10)
+}
+
+// The value of up to 19 ASCII digits, which always fits
+fn digits(text: &[u8]) -> Option<u64> {
+    text.iter()
+        .try_fold(0, |value: u64, byte| Some(value * 10 + digit(byte)? as u64))
+}
+
+// The value of any number of ASCII digits, if it fits
+fn long_digits(text: &[u8]) -> Option<u128> {
+    text.iter().try_fold(0, |value: u128, byte| {
+        value.checked_mul(10)?.checked_add(digit(byte)? as u128)
+    })
+}
+
+// With the fewest places needed, as a normalized Decimal would be
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = self.0.unsigned_abs();
+        let (whole, fraction) = (units / UNIT as u128, units % UNIT as u128);
+        let sign = if self.0 < 0 { "-" } else { "" };
+
+        if fraction == 0 {
+            return write!(f, "{sign}{whole}");
+        }
+
+        let fraction = format!("{fraction:018}");
+
+        write!(f, "{sign}{whole}.{}", fraction.trim_end_matches('0'))
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(-self.0)
+    }
+}
+
+// Places beyond those held are dropped, and out of range values saturate
+impl From<Decimal> for Fixed {
+    fn from(decimal: Decimal) -> Fixed {
+        let scale = decimal.scale();
+        let mantissa = decimal.mantissa();
+
+        let units = if scale > Fixed::SCALE {
+            Some(mantissa / 10i128.pow(scale - Fixed::SCALE))
+        } else {
+            mantissa.checked_mul(POWERS[(Fixed::SCALE - scale) as usize])
+        };
+
+        match units {
+            Some(units) => Fixed(units),
+            None if mantissa < 0 => Fixed::MIN,
+            None => Fixed::MAX,
+        }
+    }
+}
+
+// As strings, like Decimal with serde-str, so no precision is lost
+impl Serialize for Fixed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fixed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+
+        Fixed::from_str(&text).map_err(|_| de::Error::custom(format!("invalid amount '{text}'")))
+    }
+}
+
+#[cfg(test)]
+mod test_from_str {
+    use super::*;
+
+    fn parse(text: &str) -> Result<String, ParseFixedError> {
+        Fixed::from_str(text).map(|fixed| fixed.to_string())
+    }
+
+    #[test]
+    fn invalid() {
+        for text in ["", "-", ".", "1.2.3", "1,5", "abc", " 1", "1e5", "--1"] {
+            assert!(parse(text) == Err(ParseFixedError));
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(parse("170141183460469231732").is_err());
+        assert!(parse("170141183460469231731").is_ok());
+        assert!(parse("340282366920938463463.374607431768211455").is_err());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(parse("0") == Ok(String::from("0")));
+        assert!(parse("-0.0") == Ok(String::from("0")));
+        assert!(parse("1.50") == Ok(String::from("1.5")));
+        assert!(parse("+2.") == Ok(String::from("2")));
+        assert!(parse(".25") == Ok(String::from("0.25")));
+        assert!(parse("-1234.5678") == Ok(String::from("-1234.5678")));
+        assert!(parse("0.000000000000000001") == Ok(String::from("0.000000000000000001")));
+    }
+
+    #[test]
+    fn extra_places() {
+        assert!(parse("0.1234567890123456789999") == Ok(String::from("0.123456789012345678")));
+    }
+}
+
+#[cfg(test)]
+mod test_arithmetic {
+    use super::*;
+
+    fn fixed(text: &str) -> Fixed {
+        Fixed::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn add_sub() {
+        assert!(fixed("1.5").checked_add(fixed("2.25")) == Some(fixed("3.75")));
+        assert!(fixed("1.5").checked_sub(fixed("2.25")) == Some(fixed("-0.75")));
+        assert!(Fixed::MAX.checked_add(Fixed::ONE).is_none());
+        assert!(Fixed::MIN.checked_sub(Fixed::ONE).is_none());
+        assert!(Fixed::MAX.saturating_add(Fixed::ONE) == Fixed::MAX);
+        assert!(Fixed::MIN.saturating_sub(Fixed::ONE) == Fixed::MIN);
+        assert!(-Fixed::MIN == Fixed::MAX);
+    }
+
+    #[test]
+    fn mul() {
+        assert!(fixed("10").checked_mul(fixed("1.08257")) == Some(fixed("10.8257")));
+        assert!(fixed("-1.5").checked_mul(fixed("1.5")) == Some(fixed("-2.25")));
+        assert!(
+            fixed("0.000000001").checked_mul(fixed("0.000000001"))
+                == Some(fixed("0.000000000000000001"))
+        );
+        assert!(fixed("1000000000").checked_mul(Fixed::MAX).is_none());
+    }
+
+    #[test]
+    fn div() {
+        assert!(Fixed::ONE.checked_div(fixed("4")) == Some(fixed("0.25")));
+        assert!(Fixed::ONE.checked_div(fixed("3")) == Some(fixed("0.333333333333333333")));
+        assert!(fixed("-1").checked_div(fixed("0.5")) == Some(fixed("-2")));
+        assert!(Fixed::ONE.checked_div(fixed("15000")) == Some(fixed("0.000066666666666666")));
+        assert!(Fixed::ONE.checked_div(Fixed::ZERO).is_none());
+    }
+}
+
+#[cfg(test)]
+mod test_round_dp_with_strategy {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let round = |text: &str, strategy| {
+            Fixed::from_str(text)
+                .unwrap()
+                .round_dp_with_strategy(2, strategy)
+                .to_string()
+        };
+
+        // The same as Decimal for every strategy
+        for text in [
+            "1.235", "1.245", "-1.235", "-1.245", "1.2", "1.2351", "-0.001",
+        ] {
+            for strategy in [
+                RoundingStrategy::ToZero,
+                RoundingStrategy::AwayFromZero,
+                RoundingStrategy::ToNegativeInfinity,
+                RoundingStrategy::ToPositiveInfinity,
+                RoundingStrategy::MidpointAwayFromZero,
+                RoundingStrategy::MidpointTowardZero,
+                RoundingStrategy::MidpointNearestEven,
+            ] {
+                let decimal = Decimal::from_str(text)
+                    .unwrap()
+                    .round_dp_with_strategy(2, strategy)
+                    .normalize();
+
+                assert!(round(text, strategy) == decimal.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let max = Fixed::MAX.round_dp_with_strategy(0, RoundingStrategy::ToPositiveInfinity);
+        let min = Fixed::MIN.round_dp_with_strategy(0, RoundingStrategy::ToNegativeInfinity);
+
+        assert!(max == Fixed::MAX);
+        assert!(min == Fixed::MIN);
+        assert!(
+            Fixed::ONE.round_dp_with_strategy(18, RoundingStrategy::AwayFromZero) == Fixed::ONE
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_from {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn ok() {
+        assert!(Fixed::from(dec!(-1.2345)).to_string() == "-1.2345");
+        assert!(Fixed::from(Decimal::PI).to_string() == "3.141592653589793238");
+        assert!(Fixed::from(Decimal::MAX) == Fixed::MAX);
+        assert!(Fixed::from(Decimal::MIN) == Fixed::MIN);
+    }
+}
+
+#[cfg(test)]
+mod test_serialize {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let fixed = Fixed::from_str("-12.5").unwrap();
+        let json = serde_json::to_string(&fixed).unwrap();
+
+        assert!(json == r#""-12.5""#);
+        assert!(serde_json::from_str::<Fixed>(&json).unwrap() == fixed);
+        assert!(Fixed::deserialize(fixed.serialize()) == fixed);
+    }
+}
@@ -0,0 +1,298 @@
+use super::*;
+
+use csv::StringRecord;
+use rust_decimal::prelude::*;
+use std::collections::HashMap;
+
+// The rate a unit of one currency converts into another at, from a timestamp
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FxRate {
+    pub timestamp: Timestamp,
+    pub from: Currency,
+    pub to: Currency,
+    pub rate: Amount,
+}
+
+impl FxRate {
+    pub fn parse_record(record: &StringRecord) -> Result<FxRate, TransactionError> {
+        if record.len() < 4 {
+            return Err(TransactionError::MissingColumns);
+        }
+
+        let currency = |code: &str| Currency::parse(code).ok_or(TransactionError::InvalidCurrency);
+
+        Ok(FxRate {
+            timestamp: match String::from(&record[0]).parse() {
+                Ok(timestamp) => timestamp,
+                _ => return Err(TransactionError::InvalidTimestamp),
+            },
+            from: currency(&record[1])?,
+            to: currency(&record[2])?,
+            rate: match Amount::from_str(&record[3]) {
+                Ok(rate) if rate > Amount::ZERO => rate,
+                _ => return Err(TransactionError::InvalidAmount),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FxRates {
+    // Each pair's rates, ordered by the time they apply from
+    rates: HashMap<(Currency, Currency), Vec<(Timestamp, Amount)>>,
+}
+
+impl FxRates {
+    pub fn new() -> Self {
+        FxRates {
+            rates: HashMap::new(),
+        }
+    }
+
+    // A later rate for the same pair and timestamp replaces the earlier one
+    pub fn add(&mut self, fx_rate: FxRate) {
+        let rates = self.rates.entry((fx_rate.from, fx_rate.to)).or_default();
+
+        match rates.binary_search_by_key(&fx_rate.timestamp, |(timestamp, _)| *timestamp) {
+            Ok(index) => rates[index].1 = fx_rate.rate,
+            Err(index) => rates.insert(index, (fx_rate.timestamp, fx_rate.rate)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    // The latest rate in force at the timestamp, or the latest of all without
+    // one. A pair only given the other way round uses the inverse rate.
+    pub fn rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        timestamp: Option<Timestamp>,
+    ) -> Option<Amount> {
+        let latest = |pair: &(Currency, Currency)| {
+            let rates = self.rates.get(pair)?;
+            let count = match timestamp {
+                Some(timestamp) => rates.partition_point(|(from, _)| *from <= timestamp),
+                None => rates.len(),
+            };
+
+            count.checked_sub(1).map(|index| rates[index].1)
+        };
+
+        match latest(&(from, to)) {
+            Some(rate) => Some(rate),
+            None => Amount::ONE.checked_div(latest(&(to, from))?),
+        }
+    }
+
+    // Converted amounts are rounded like parsed ones
+    pub fn convert(
+        &self,
+        amount: Amount,
+        from: Currency,
+        to: Currency,
+        timestamp: Option<Timestamp>,
+        money: &Money,
+    ) -> Result<Amount, TransactionError> {
+        let rate = self
+            .rate(from, to, timestamp)
+            .ok_or(TransactionError::RateNotFound)?;
+
+        let converted = amount.checked_mul(rate).ok_or(TransactionError::Overflow)?;
+
+        match money.round(converted) {
+            converted if converted > Amount::ZERO => Ok(converted),
+            // Too small to be worth anything in the other currency
+            _ => Err(TransactionError::NonPositiveAmount),
+        }
+    }
+}
+
+impl Default for FxRates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_parse_record {
+    use super::*;
+
+    #[test]
+    fn three_cols() {
+        let record = StringRecord::from(vec!["0", "EUR", "USD"]);
+
+        assert!(FxRate::parse_record(&record) == Err(TransactionError::MissingColumns));
+    }
+
+    #[test]
+    fn invalid() {
+        for (fields, error) in [
+            (
+                vec!["-1", "EUR", "USD", "1.1"],
+                TransactionError::InvalidTimestamp,
+            ),
+            (
+                vec!["0", "EURO", "USD", "1.1"],
+                TransactionError::InvalidCurrency,
+            ),
+            (
+                vec!["0", "EUR", "", "1.1"],
+                TransactionError::InvalidCurrency,
+            ),
+            (
+                vec!["0", "EUR", "USD", "0"],
+                TransactionError::InvalidAmount,
+            ),
+            (
+                vec!["0", "EUR", "USD", "abc"],
+                TransactionError::InvalidAmount,
+            ),
+        ] {
+            let record = StringRecord::from(fields);
+
+            assert!(FxRate::parse_record(&record) == Err(error));
+        }
+    }
+
+    #[test]
+    fn ok() {
+        let record = StringRecord::from(vec!["1700000000", "eur", "USD", "1.0825"]);
+
+        assert!(
+            FxRate::parse_record(&record)
+                == Ok(FxRate {
+                    timestamp: 1700000000,
+                    from: Currency::parse("EUR").unwrap(),
+                    to: Currency::parse("USD").unwrap(),
+                    rate: amount!(1.0825),
+                })
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_rate {
+    use super::*;
+
+    fn currency(code: &str) -> Currency {
+        Currency::parse(code).unwrap()
+    }
+
+    fn fx_rates() -> FxRates {
+        let mut fx_rates = FxRates::new();
+
+        for (timestamp, rate) in [
+            (200, amount!(1.2)),
+            (100, amount!(1.1)),
+            (300, amount!(1.3)),
+        ] {
+            fx_rates.add(FxRate {
+                timestamp,
+                from: currency("EUR"),
+                to: currency("USD"),
+                rate,
+            });
+        }
+
+        fx_rates
+    }
+
+    #[test]
+    fn by_timestamp() {
+        let fx_rates = fx_rates();
+        let rate = |timestamp| fx_rates.rate(currency("EUR"), currency("USD"), timestamp);
+
+        assert!(rate(Some(99)).is_none());
+        assert!(rate(Some(100)) == Some(amount!(1.1)));
+        assert!(rate(Some(250)) == Some(amount!(1.2)));
+        assert!(rate(Some(1000)) == Some(amount!(1.3)));
+        assert!(rate(None) == Some(amount!(1.3)));
+    }
+
+    #[test]
+    fn replaced() {
+        let mut fx_rates = fx_rates();
+
+        fx_rates.add(FxRate {
+            timestamp: 200,
+            from: currency("EUR"),
+            to: currency("USD"),
+            rate: amount!(1.25),
+        });
+
+        assert!(fx_rates.rate(currency("EUR"), currency("USD"), Some(200)) == Some(amount!(1.25)));
+    }
+
+    #[test]
+    fn inverse() {
+        let fx_rates = fx_rates();
+
+        assert!(
+            fx_rates.rate(currency("USD"), currency("EUR"), Some(100))
+                == Amount::ONE.checked_div(amount!(1.1))
+        );
+        assert!(fx_rates
+            .rate(currency("USD"), currency("GBP"), None)
+            .is_none());
+    }
+}
+
+#[cfg(test)]
+mod test_convert {
+    use super::*;
+
+    fn fx_rates() -> FxRates {
+        let mut fx_rates = FxRates::new();
+
+        fx_rates.add(FxRate {
+            timestamp: 100,
+            from: Currency::parse("EUR").unwrap(),
+            to: Currency::parse("USD").unwrap(),
+            rate: amount!(1.08257),
+        });
+
+        fx_rates
+    }
+
+    fn convert(amount: Amount, timestamp: Option<Timestamp>) -> Result<Amount, TransactionError> {
+        fx_rates().convert(
+            amount,
+            Currency::parse("EUR").unwrap(),
+            Currency::parse("USD").unwrap(),
+            timestamp,
+            &Money::new(),
+        )
+    }
+
+    #[test]
+    fn rate_not_found() {
+        assert!(convert(amount!(10.0), Some(99)) == Err(TransactionError::RateNotFound));
+    }
+
+    #[test]
+    fn too_small() {
+        let converted = fx_rates().convert(
+            amount!(0.0001),
+            Currency::parse("USD").unwrap(),
+            Currency::parse("EUR").unwrap(),
+            None,
+            &Money::new(),
+        );
+
+        assert!(converted == Err(TransactionError::NonPositiveAmount));
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(convert(Amount::MAX, None) == Err(TransactionError::Overflow));
+    }
+
+    #[test]
+    fn ok() {
+        assert!(convert(amount!(10.0), Some(100)) == Ok(amount!(10.8257)));
+        assert!(convert(amount!(1.5), None) == Ok(amount!(1.6238)));
+    }
+}
@@ -0,0 +1,76 @@
+use csv::{ReaderBuilder, Trim};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InputFormat {
+    // CSV with a header row
+    #[default]
+    Csv,
+    // One JSON object per line
+    JsonLines,
+}
+
+impl InputFormat {
+    pub fn parse(format: &str) -> Option<InputFormat> {
+        match format {
+            "csv" => Some(InputFormat::Csv),
+            "jsonl" => Some(InputFormat::JsonLines),
+            _ => None,
+        }
+    }
+
+    pub fn reader_builder(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+
+        builder.flexible(true).trim(Trim::All);
+
+        if *self == InputFormat::JsonLines {
+            // Each line is read as a single field, as JSON text can't contain
+            // a raw unit separator, so positions still work for resuming
+            builder.has_headers(false).delimiter(b'\x1f').quoting(false);
+        }
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(InputFormat::parse("json").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(InputFormat::parse("csv") == Some(InputFormat::Csv));
+        assert!(InputFormat::parse("jsonl") == Some(InputFormat::JsonLines));
+    }
+}
+
+#[cfg(test)]
+mod test_reader_builder {
+    use super::*;
+    use csv::StringRecord;
+
+    #[test]
+    fn json_lines() {
+        let input = "{\"type\": \"deposit\", \"client\": 1}\n\n{\"tx\": \"a,b\"}\n";
+
+        let records = InputFormat::JsonLines
+            .reader_builder()
+            .from_reader(input.as_bytes())
+            .into_records()
+            .map(|record| record.unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(
+            records
+                == vec![
+                    StringRecord::from(vec![r#"{"type": "deposit", "client": 1}"#]),
+                    StringRecord::from(vec![r#"{"tx": "a,b"}"#]),
+                ]
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    // Which of the inputs this came from, and how many lines the ones before
+    // it had; journals from before multiple inputs only ever had the one
+    #[serde(default)]
+    pub input: usize,
+    #[serde(default)]
+    pub line_offset: u64,
+    // Where the input continues after this transaction, counting lines
+    // across all the inputs
+    pub byte: u64,
+    pub line: u64,
+    pub record: u64,
+    pub transaction: Transaction,
+    // Only restores the empty account the rejected transaction created, and
+    // isn't a point to resume from, so the row is rejected again on recovery
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rejected: bool,
+}
+
+// Somewhere writes can be made to survive the machine dying, not just the
+// process
+pub trait Durable: Write {
+    fn sync(&self) -> io::Result<()>;
+}
+
+impl Durable for File {
+    fn sync(&self) -> io::Result<()> {
+        self.sync_data()
+    }
+}
+
+impl Durable for Vec<u8> {
+    fn sync(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Journal<W: Durable>(W);
+
+impl<W: Durable> Journal<W> {
+    pub fn new(writer: W) -> Self {
+        Journal(writer)
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+
+        line.push(b'\n');
+
+        // A single write per entry, so a crash can only tear the last one,
+        // and synced before the transaction is committed
+        self.0.write_all(&line)?;
+        self.0.flush()?;
+        self.0.sync()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Replay {
+    pub last: Option<JournalEntry>,
+    // Length of the complete entries, anything after them was torn
+    pub length: u64,
+}
+
+impl Ledger {
+    pub fn apply_journaled<W: Durable>(
+        &mut self,
+        entry: JournalEntry,
+        journal: &mut Journal<W>,
+    ) -> io::Result<Result<(), TransactionError>> {
+        let client_id = entry.transaction.client_id;
+        let known = self.accounts.contains_key(&client_id);
+
+        let account = match self.stage(&entry.transaction) {
+            Ok(account) => account,
+            Err(err) => {
+                // The client is still reported, so recovery has to know of it
+                if !known && self.accounts.contains_key(&client_id) {
+                    journal.append(&JournalEntry {
+                        rejected: true,
+                        ..entry
+                    })?;
+                }
+
+                return Ok(Err(err));
+            }
+        };
+
+        // The transaction logs are already staged at this point, so the
+        // ledger can't be used any further if the journal can't be written
+        journal.append(&entry)?;
+
+        self.commit(entry.transaction.client_id, account);
+
+        Ok(Ok(()))
+    }
+
+    pub fn replay<R: BufRead>(&mut self, mut reader: R) -> Result<Replay, SnapshotError> {
+        let mut replay = Replay {
+            last: None,
+            length: 0,
+        };
+
+        let mut line = Vec::new();
+        let mut number = 0;
+
+        loop {
+            line.clear();
+
+            let read = reader.read_until(b'\n', &mut line)?;
+
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+
+            number += 1;
+
+            let entry: JournalEntry = serde_json::from_slice(&line)?;
+
+            replay.length += read as u64;
+
+            if entry.rejected {
+                self.accounts.find_or_create(entry.transaction.client_id);
+                continue;
+            }
+
+            self.apply(entry.transaction.clone())
+                .map_err(|err| SnapshotError::Replay(number, err))?;
+
+            replay.last = Some(entry);
+        }
+
+        // Warnings were already reported when the entries were first applied
+        self.warnings.clear();
+
+        Ok(replay)
+    }
+}
+
+#[cfg(test)]
+mod test_append {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut journal = Journal::new(Vec::new());
+
+        let result = journal.append(&JournalEntry {
+            input: 0,
+            line_offset: 0,
+            byte: 38,
+            line: 3,
+            record: 2,
+            transaction: Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.5),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            },
+            rejected: false,
+        });
+
+        assert!(result.is_ok());
+
+        assert!(
+            String::from_utf8(journal.into_inner()).unwrap()
+                == concat!(
+                    r#"{"input":0,"line_offset":0,"byte":38,"line":3,"record":2,"transaction":"#,
+                    r#"{"id":1,"client_id":2,"kind":"deposit","amount":"1.5","reason":null}}"#,
+                    "\n"
+                )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_apply_journaled {
+    use super::*;
+
+    fn entry(id: TransactionId, kind: TransactionKind, amount: Amount) -> JournalEntry {
+        JournalEntry {
+            input: 0,
+            line_offset: 0,
+            byte: id as u64 * 10,
+            line: id as u64 + 1,
+            record: id as u64,
+            transaction: Transaction {
+                id,
+                client_id: 1,
+                kind,
+                amount,
+                currency: None,
+                timestamp: None,
+                reason: None,
+            },
+            rejected: false,
+        }
+    }
+
+    #[test]
+    fn rejected() {
+        let mut ledger = Ledger::new();
+        let mut journal = Journal::new(Vec::new());
+
+        assert!(
+            ledger.apply(entry(1, TransactionKind::Deposit, amount!(1.0)).transaction) == Ok(())
+        );
+
+        let result = ledger.apply_journaled(
+            entry(2, TransactionKind::Withdrawal, amount!(2.0)),
+            &mut journal,
+        );
+
+        assert!(matches!(
+            result,
+            Ok(Err(TransactionError::InsufficientFunds))
+        ));
+        assert!(journal.into_inner().is_empty());
+    }
+
+    #[test]
+    fn rejected_new_client() {
+        let mut ledger = Ledger::new();
+        let mut journal = Journal::new(Vec::new());
+
+        let result = ledger.apply_journaled(
+            entry(1, TransactionKind::Withdrawal, amount!(1.0)),
+            &mut journal,
+        );
+
+        assert!(matches!(
+            result,
+            Ok(Err(TransactionError::InsufficientFunds))
+        ));
+
+        // Journaled only so that recovery reports the client as well
+        let journal = journal.into_inner();
+
+        assert!(journal.ends_with(b",\"rejected\":true}\n"));
+
+        let mut recovered = Ledger::new();
+        let replay = recovered.replay(journal.as_slice()).unwrap();
+
+        assert!(recovered == ledger);
+        assert!(replay.last.is_none());
+        assert!(replay.length == journal.len() as u64);
+    }
+
+    #[test]
+    fn ok() {
+        let mut ledger = Ledger::new();
+        let mut journal = Journal::new(Vec::new());
+
+        let result = ledger.apply_journaled(
+            entry(1, TransactionKind::Deposit, amount!(1.0)),
+            &mut journal,
+        );
+
+        assert!(matches!(result, Ok(Ok(()))));
+        assert!(ledger.accounts.get(&1).map(|a| a.wallet(None).available) == Some(amount!(1.0)));
+        assert!(journal.into_inner().split(|b| *b == b'\n').count() == 2);
+    }
+}
+
+#[cfg(test)]
+mod test_replay {
+    use super::*;
+    use csv::StringRecord;
+
+    fn journal(entries: &[(TransactionId, TransactionKind, Amount)]) -> Vec<u8> {
+        let mut journal = Journal::new(Vec::new());
+
+        for (id, kind, amount) in entries {
+            let entry = JournalEntry {
+                input: 0,
+                line_offset: 0,
+                byte: *id as u64 * 10,
+                line: *id as u64 + 1,
+                record: *id as u64,
+                transaction: Transaction {
+                    id: *id,
+                    client_id: 1,
+                    kind: *kind,
+                    amount: *amount,
+                    currency: None,
+                    timestamp: None,
+                    reason: None,
+                },
+                rejected: false,
+            };
+
+            assert!(journal.append(&entry).is_ok());
+        }
+
+        journal.into_inner()
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let journal = journal(&[
+            (1, TransactionKind::Deposit, amount!(5.0)),
+            (2, TransactionKind::Withdrawal, amount!(2.0)),
+        ]);
+
+        let mut expected_ledger = Ledger::new();
+
+        for record in [
+            ["deposit", "1", "1", "5.0"],
+            ["withdrawal", "1", "2", "2.0"],
+        ] {
+            assert!(expected_ledger.apply_record(&StringRecord::from(record.to_vec())) == Ok(()));
+        }
+
+        //
+        // action
+        //
+
+        let mut ledger = Ledger::new();
+        let replay = ledger.replay(journal.as_slice()).unwrap();
+
+        //
+        // test what we expect
+        //
+
+        assert!(ledger == expected_ledger);
+        assert!(replay.length == journal.len() as u64);
+        assert!(
+            replay
+                .last
+                .map(|entry| (entry.byte, entry.line, entry.record))
+                == Some((20, 3, 2))
+        );
+    }
+
+    #[test]
+    fn single_input() {
+        let journal = concat!(
+            r#"{"byte":38,"line":3,"record":2,"transaction":"#,
+            r#"{"id":1,"client_id":2,"kind":"deposit","amount":"1.5","reason":null}}"#,
+            "\n"
+        );
+
+        let mut ledger = Ledger::new();
+        let replay = ledger.replay(journal.as_bytes()).unwrap();
+
+        assert!(
+            replay
+                .last
+                .map(|entry| (entry.input, entry.line_offset, entry.line))
+                == Some((0, 0, 3))
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let mut ledger = Ledger::new();
+        let replay = ledger.replay(&b""[..]).unwrap();
+
+        assert!(replay.last.is_none());
+        assert!(replay.length == 0);
+        assert!(ledger == Ledger::new());
+    }
+
+    #[test]
+    fn torn() {
+        //
+        // setup
+        //
+
+        let mut journal = journal(&[
+            (1, TransactionKind::Deposit, amount!(5.0)),
+            (2, TransactionKind::Deposit, amount!(2.0)),
+        ]);
+
+        let length = journal.iter().position(|b| *b == b'\n').unwrap() + 1;
+
+        journal.truncate(journal.len() - 10);
+
+        //
+        // action
+        //
+
+        let mut ledger = Ledger::new();
+        let replay = ledger.replay(journal.as_slice()).unwrap();
+
+        //
+        // test what we expect
+        //
+
+        assert!(replay.length == length as u64);
+        assert!(replay.last.map(|entry| entry.transaction.id) == Some(1));
+        assert!(ledger.accounts.get(&1).map(|a| a.wallet(None).available) == Some(amount!(5.0)));
+    }
+
+    #[test]
+    fn refused() {
+        let journal = journal(&[
+            (1, TransactionKind::Deposit, amount!(5.0)),
+            (1, TransactionKind::Deposit, amount!(5.0)),
+        ]);
+
+        let mut ledger = Ledger::new();
+        let result = ledger.replay(journal.as_slice());
+
+        assert!(matches!(
+            result,
+            Err(SnapshotError::Replay(
+                2,
+                TransactionError::DuplicateTransaction
+            ))
+        ));
+    }
+
+    #[test]
+    fn invalid() {
+        let mut ledger = Ledger::new();
+        let result = ledger.replay(&b"{\"byte\":1}\n"[..]);
+
+        assert!(matches!(result, Err(SnapshotError::Format(_))));
+    }
+}
@@ -0,0 +1,1045 @@
+use super::*;
+
+use csv::StringRecord;
+
+#[derive(PartialEq)]
+pub struct Ledger {
+    pub config: Config,
+    pub accounts: Accounts,
+    pub transaction_logs: TransactionLogs,
+    pub warnings: Warnings,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::with_config(Config::new())
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        Ledger {
+            transaction_logs: TransactionLogs::with_config(&config),
+            config,
+            accounts: Accounts::new(),
+            warnings: Warnings::new(),
+        }
+    }
+
+    pub fn apply(&mut self, transaction: Transaction) -> Result<(), TransactionError> {
+        let account = self.stage(&transaction)?;
+
+        self.commit(transaction.client_id, account);
+
+        Ok(())
+    }
+
+    // Checks the transaction and updates the transaction logs, returning the
+    // account as it is once the transaction is committed
+    pub fn stage(&mut self, transaction: &Transaction) -> Result<Account, TransactionError> {
+        let mut account = if transaction.kind.is_admin() {
+            match self.accounts.get(&transaction.client_id) {
+                Some(account) => account.clone(),
+                None => return Err(TransactionError::AccountNotFound),
+            }
+        } else {
+            self.accounts.find_or_create(transaction.client_id)
+        };
+
+        account
+            .status
+            .permits(transaction.kind, &self.config.status_policy)?;
+
+        let transaction_logs = &mut self.transaction_logs;
+        let config = &self.config;
+        let warnings = &mut self.warnings;
+
+        match transaction.kind {
+            TransactionKind::Deposit => {
+                account.deposit(transaction, transaction_logs, config, warnings)
+            }
+            TransactionKind::Withdrawal => {
+                account.withdrawal(transaction, transaction_logs, config, warnings)
+            }
+            TransactionKind::Dispute => {
+                account.dispute(transaction, transaction_logs, config, warnings)
+            }
+            TransactionKind::Resolve => {
+                account.resolve(transaction, transaction_logs, config, warnings)
+            }
+            TransactionKind::Chargeback => {
+                account.chargeback(transaction, transaction_logs, config, warnings)
+            }
+            TransactionKind::Freeze
+            | TransactionKind::Unfreeze
+            | TransactionKind::Unlock
+            | TransactionKind::Close => account.change_status(transaction),
+        }?;
+
+        Ok(account)
+    }
+
+    pub fn commit(&mut self, client_id: ClientId, account: Account) {
+        self.accounts.insert(client_id, account);
+    }
+
+    pub fn apply_metadata(&mut self, client_metadata: ClientMetadata) {
+        let mut account = self.accounts.find_or_create(client_metadata.client_id);
+
+        account.credit_limit = client_metadata.credit_limit;
+        account.base_currency = client_metadata.base_currency;
+
+        self.accounts.insert(client_metadata.client_id, account);
+    }
+
+    pub fn apply_record(&mut self, record: &StringRecord) -> Result<(), TransactionError> {
+        self.apply(Transaction::parse_record(record, &self.config.money)?)
+    }
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_new {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let ledger = Ledger::new();
+
+        assert!(ledger.config == Config::new());
+        assert!(ledger.accounts == Accounts::new());
+        assert!(ledger.transaction_logs == TransactionLogs::new());
+        assert!(ledger.warnings.is_empty());
+    }
+
+    #[test]
+    fn default() {
+        assert!(Ledger::default() == Ledger::new());
+    }
+}
+
+#[cfg(test)]
+mod test_with_config {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                deposit: DisputeMode::HoldFunds,
+                withdrawal: DisputeMode::Refuse,
+                allow_redispute: true,
+                window: None,
+            },
+            ..Config::new()
+        };
+
+        let ledger = Ledger::with_config(config.clone());
+
+        assert!(ledger.config == config);
+        assert!(ledger.accounts == Accounts::new());
+    }
+}
+
+#[cfg(test)]
+mod test_apply {
+    use super::*;
+
+    #[test]
+    fn deposit_then_withdrawal() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        //
+        // action
+        //
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(5.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let result = ledger.apply(Transaction {
+            id: 2,
+            client_id: 1,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(2.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(3.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+
+        assert!(ledger.transaction_logs.len() == 2);
+    }
+
+    #[test]
+    fn dispute_window_expired() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::with_config(Config {
+            dispute_policy: DisputePolicy {
+                window: Some(2),
+                ..DisputePolicy::new()
+            },
+            ..Config::new()
+        });
+
+        let transaction = |id, kind| Transaction {
+            id,
+            client_id: 1,
+            kind,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        assert!(ledger.apply(transaction(1, TransactionKind::Deposit)) == Ok(()));
+        assert!(ledger.apply(transaction(2, TransactionKind::Deposit)) == Ok(()));
+        assert!(ledger.apply(transaction(2, TransactionKind::Dispute)) == Ok(()));
+
+        //
+        // action
+        //
+
+        for id in 3..=4 {
+            assert!(ledger.apply(transaction(id, TransactionKind::Deposit)) == Ok(()));
+        }
+
+        //
+        // test what we expect
+        //
+
+        // The disputed deposit stays in the window until it is settled
+        assert!(ledger.transaction_logs.len() == 2);
+        assert!(
+            ledger.apply(transaction(1, TransactionKind::Dispute))
+                == Err(TransactionError::DisputeWindowExpired)
+        );
+        assert!(
+            ledger.apply(transaction(1, TransactionKind::Deposit))
+                == Err(TransactionError::DuplicateTransaction)
+        );
+        assert!(ledger.apply(transaction(2, TransactionKind::Resolve)) == Ok(()));
+        assert!(ledger.apply(transaction(4, TransactionKind::Dispute)) == Ok(()));
+    }
+
+    #[test]
+    fn dispute_then_chargeback() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(5.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        //
+        // action
+        //
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Dispute,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let result = ledger.apply(Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Chargeback,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(0.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+
+        assert!(
+            ledger.transaction_logs.get(1).unwrap().map(|t| t.state)
+                == Some(TransactionState::ChargedBack)
+        );
+    }
+
+    #[test]
+    fn dispute_twice() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(5.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let dispute = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        //
+        // action
+        //
+
+        assert!(ledger.apply(dispute.clone()) == Ok(()));
+
+        let result = ledger.apply(dispute);
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::AlreadyDisputed));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(0.0),
+                        held: amount!(5.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+    }
+
+    #[test]
+    fn locked() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        ledger.accounts.insert(
+            1,
+            Account {
+                wallets: vec![Wallet {
+                    currency: None,
+                    available: amount!(5.0),
+                    held: amount!(0.0),
+                    debt: amount!(0.0),
+                }],
+                status: AccountStatus::Locked,
+                credit_limit: amount!(0.0),
+                base_currency: None,
+            },
+        );
+
+        //
+        // action
+        //
+
+        let result = ledger.apply(Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(5.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::AccountLocked));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+
+        assert!(ledger.transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn locked_resolve() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        for (id, kind) in [(1, TransactionKind::Deposit), (2, TransactionKind::Deposit)] {
+            assert!(
+                ledger.apply(Transaction {
+                    id,
+                    client_id: 1,
+                    kind,
+                    amount: amount!(5.0),
+                    currency: None,
+                    timestamp: None,
+                    reason: None,
+                }) == Ok(())
+            );
+        }
+
+        for (id, kind) in [
+            (1, TransactionKind::Dispute),
+            (2, TransactionKind::Dispute),
+            (1, TransactionKind::Chargeback),
+        ] {
+            assert!(
+                ledger.apply(Transaction {
+                    id,
+                    client_id: 1,
+                    kind,
+                    amount: amount!(0.0),
+                    currency: None,
+                    timestamp: None,
+                    reason: None,
+                }) == Ok(())
+            );
+        }
+
+        //
+        // action
+        //
+
+        let result = ledger.apply(Transaction {
+            id: 2,
+            client_id: 1,
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+    }
+
+    #[test]
+    fn locked_resolve_denied() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::with_config(Config {
+            status_policy: StatusPolicy {
+                locked: Vec::new(),
+                ..StatusPolicy::new()
+            },
+            ..Config::new()
+        });
+
+        ledger.accounts.insert(
+            1,
+            Account {
+                wallets: vec![Wallet {
+                    currency: None,
+                    available: amount!(0.0),
+                    held: amount!(5.0),
+                    debt: amount!(0.0),
+                }],
+                status: AccountStatus::Locked,
+                credit_limit: amount!(0.0),
+                base_currency: None,
+            },
+        );
+
+        //
+        // action
+        //
+
+        let result = ledger.apply(Transaction {
+            id: 2,
+            client_id: 1,
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Err(TransactionError::AccountLocked));
+    }
+
+    #[test]
+    fn freeze_then_unfreeze() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(5.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let deposit = Transaction {
+            id: 2,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        //
+        // action
+        //
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 0,
+                client_id: 1,
+                kind: TransactionKind::Freeze,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: Some(String::from("suspicious activity")),
+            }) == Ok(())
+        );
+
+        let frozen = ledger.apply(deposit.clone());
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 0,
+                client_id: 1,
+                kind: TransactionKind::Unfreeze,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: Some(String::from("cleared")),
+            }) == Ok(())
+        );
+
+        let active = ledger.apply(deposit);
+
+        //
+        // test what we expect
+        //
+
+        assert!(frozen == Err(TransactionError::AccountFrozen));
+        assert!(active == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(6.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+
+        assert!(ledger.transaction_logs.len() == 2);
+    }
+
+    #[test]
+    fn unlock() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        ledger.accounts.insert(
+            1,
+            Account {
+                wallets: vec![Wallet {
+                    currency: None,
+                    available: amount!(5.0),
+                    held: amount!(0.0),
+                    debt: amount!(0.0),
+                }],
+                status: AccountStatus::Locked,
+                credit_limit: amount!(0.0),
+                base_currency: None,
+            },
+        );
+
+        //
+        // action
+        //
+
+        let result = ledger.apply(Transaction {
+            id: 0,
+            client_id: 1,
+            kind: TransactionKind::Unlock,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("chargeback reviewed")),
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(5.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+    }
+
+    #[test]
+    fn closed() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        ledger.accounts.insert(
+            1,
+            Account {
+                wallets: vec![Wallet {
+                    currency: None,
+                    available: amount!(5.0),
+                    held: amount!(0.0),
+                    debt: amount!(0.0),
+                }],
+                status: AccountStatus::Closed,
+                credit_limit: amount!(0.0),
+                base_currency: None,
+            },
+        );
+
+        //
+        // action
+        //
+
+        let deposit = ledger.apply(Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(5.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        let unlock = ledger.apply(Transaction {
+            id: 0,
+            client_id: 1,
+            kind: TransactionKind::Unlock,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("reopen")),
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(deposit == Err(TransactionError::AccountClosed));
+        assert!(unlock == Err(TransactionError::AccountClosed));
+        assert!(ledger.accounts.get(&1).map(|a| a.status) == Some(AccountStatus::Closed));
+    }
+
+    #[test]
+    fn dispute_then_close() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(10.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Dispute,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        //
+        // action
+        //
+
+        let close = ledger.apply(Transaction {
+            id: 0,
+            client_id: 1,
+            kind: TransactionKind::Close,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("customer request")),
+        });
+
+        let resolve = ledger.apply(Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(close == Err(TransactionError::DisputesOpen));
+        assert!(resolve == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(10.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+    }
+
+    #[test]
+    fn admin_unknown_account() {
+        let mut ledger = Ledger::new();
+
+        let result = ledger.apply(Transaction {
+            id: 0,
+            client_id: 1,
+            kind: TransactionKind::Freeze,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("suspicious activity")),
+        });
+
+        assert!(result == Err(TransactionError::AccountNotFound));
+        assert!(ledger == Ledger::new());
+    }
+}
+
+#[cfg(test)]
+mod test_apply_metadata {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        //
+        // action
+        //
+
+        ledger.apply_metadata(ClientMetadata {
+            client_id: 1,
+            credit_limit: amount!(10.0),
+            base_currency: None,
+        });
+
+        let result = ledger.apply(Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(4.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        });
+
+        //
+        // test what we expect
+        //
+
+        assert!(result == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(-4.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(10.0),
+                    base_currency: None,
+                })
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_apply_record {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let mut ledger = Ledger::new();
+        let result = ledger.apply_record(&StringRecord::new());
+
+        assert!(result == Err(TransactionError::MissingColumns));
+        assert!(ledger == Ledger::new());
+    }
+
+    #[test]
+    fn unknown_transaction_type() {
+        let mut ledger = Ledger::new();
+        let record = StringRecord::from(vec!["desposit", "1", "1", "1.0"]);
+
+        assert!(ledger.apply_record(&record) == Err(TransactionError::UnknownTransactionType));
+        assert!(ledger == Ledger::new());
+    }
+
+    #[test]
+    fn invalid_record() {
+        let mut ledger = Ledger::new();
+        let record = StringRecord::from(vec!["deposit", "1", "1", "-1.0"]);
+
+        assert!(ledger.apply_record(&record) == Err(TransactionError::NonPositiveAmount));
+        assert!(ledger == Ledger::new());
+    }
+
+    #[test]
+    fn ok() {
+        let mut ledger = Ledger::new();
+        let record = StringRecord::from(vec!["deposit", "1", "1", "1.0"]);
+
+        assert!(ledger.apply_record(&record) == Ok(()));
+
+        assert!(
+            ledger.accounts.get(&1)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(1.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    }],
+                    status: AccountStatus::Active,
+                    credit_limit: amount!(0.0),
+                    base_currency: None,
+                })
+        );
+    }
+
+    #[test]
+    fn currencies() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        for record in [
+            vec!["deposit", "1", "1", "10.0", "EUR"],
+            vec!["deposit", "1", "2", "5.0", "gbp"],
+            vec!["withdrawal", "1", "3", "4.0", "EUR"],
+            vec!["dispute", "1", "2"],
+        ] {
+            assert!(ledger.apply_record(&StringRecord::from(record)) == Ok(()));
+        }
+
+        //
+        // action
+        //
+
+        let withdrawal = StringRecord::from(vec!["withdrawal", "1", "4", "1.0", "GBP"]);
+        let chargeback = StringRecord::from(vec!["chargeback", "1", "2", "", "EUR"]);
+
+        //
+        // test what we expect
+        //
+
+        // The GBP deposit is held, and EUR funds can't cover a GBP withdrawal
+        assert!(ledger.apply_record(&withdrawal) == Err(TransactionError::InsufficientFunds));
+        assert!(ledger.apply_record(&chargeback) == Err(TransactionError::CurrencyMismatch));
+
+        assert!(
+            ledger
+                .accounts
+                .get(&1)
+                .map(|account| account.wallets.clone())
+                == Some(vec![
+                    Wallet {
+                        currency: Currency::parse("EUR"),
+                        available: amount!(6.0),
+                        held: amount!(0.0),
+                        debt: amount!(0.0),
+                    },
+                    Wallet {
+                        currency: Currency::parse("GBP"),
+                        available: amount!(0.0),
+                        held: amount!(5.0),
+                        debt: amount!(0.0),
+                    },
+                ])
+        );
+    }
+}
@@ -0,0 +1,40 @@
+pub mod account;
+pub mod account_status;
+pub mod accounts;
+pub mod balance_writer;
+pub mod client_metadata;
+pub mod columns;
+pub mod compression;
+pub mod config;
+pub mod currency;
+pub mod disk_store;
+pub mod dispute_policy;
+pub mod fixed;
+pub mod fx_rates;
+pub mod input_format;
+pub mod journal;
+pub mod ledger;
+pub mod libs;
+pub mod memory_store;
+pub mod money;
+pub mod outcome;
+pub mod output_format;
+pub mod overflow_policy;
+pub mod rejects;
+pub mod seen_ids;
+pub mod sharded_ledger;
+pub mod snapshot;
+pub mod sort_by;
+pub mod status_policy;
+pub mod transaction;
+pub mod transaction_error;
+pub mod transaction_kind;
+pub mod transaction_log_store;
+pub mod transaction_logs;
+pub mod transaction_state;
+pub mod warning;
+
+pub use crate::libs::*;
+
+use rust_decimal::prelude::*;
+use std::collections::HashMap;
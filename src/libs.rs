@@ -1,122 +1,53 @@
 pub use crate::account::*;
+pub use crate::account_status::*;
 pub use crate::accounts::*;
+pub use crate::balance_writer::*;
+pub use crate::client_metadata::*;
+pub use crate::columns::*;
+pub use crate::compression::*;
+pub use crate::config::*;
+pub use crate::currency::*;
+pub use crate::disk_store::*;
+pub use crate::dispute_policy::*;
+pub use crate::fixed::*;
+pub use crate::fx_rates::*;
+pub use crate::input_format::*;
+pub use crate::journal::*;
+pub use crate::ledger::*;
+pub use crate::memory_store::*;
+pub use crate::money::*;
+pub use crate::outcome::*;
+pub use crate::output_format::*;
+pub use crate::overflow_policy::*;
+pub use crate::rejects::*;
+pub use crate::seen_ids::*;
+pub use crate::sharded_ledger::*;
+pub use crate::snapshot::*;
+pub use crate::sort_by::*;
+pub use crate::status_policy::*;
 pub use crate::transaction::*;
+pub use crate::transaction_error::*;
+pub use crate::transaction_kind::*;
+pub use crate::transaction_log_store::*;
 pub use crate::transaction_logs::*;
-
-use lazy_static::lazy_static;
-use regex::Regex;
-use rust_decimal::prelude::*;
-use std::collections::HashSet;
-
-pub type Amount = Decimal;
+pub use crate::transaction_state::*;
+pub use crate::warning::*;
+
+#[cfg(not(feature = "fixed-point"))]
+pub type Amount = rust_decimal::Decimal;
+// Faster, but limited to 18 decimal places and about 1.7e20
+#[cfg(feature = "fixed-point")]
+pub type Amount = Fixed;
 pub type ClientId = u16;
-pub type Disputes = HashSet<TransactionId>;
+// Seconds since the Unix epoch
+pub type Timestamp = u64;
 pub type TransactionId = u32;
-
-lazy_static! {
-    // We can safely unwrap here as this could only panic at compile time
-    static ref TRUNCATE_TO_FOUR: Regex = Regex::new(r"(^-?\d+$|^-?\d+[.]\d{0,4}).*").unwrap();
-}
-
-pub fn format(decimal: Decimal) -> String {
-    TRUNCATE_TO_FOUR
-        .captures(&decimal.to_string())
-        .and_then(|captures| captures.get(1))
-        .and_then(|decimal| Decimal::from_str(decimal.as_str()).ok())
-        .map(|decimal| decimal.normalize())
-        .unwrap_or(Decimal::ZERO)
-        .to_string() // We can safely unwrap here as the `unwrap_or()` covers us
-}
-
-#[cfg(test)]
-mod test_format {
-    use super::*;
-
-    #[test]
-    fn negitive_one() {
-        let formatted = format(Decimal::NEGATIVE_ONE);
-        assert!(formatted == "-1");
-    }
-
-    #[test]
-    fn negative_zero() {
-        let mut negative_zero = Decimal::ZERO.clone();
-        negative_zero.set_sign_negative(true);
-
-        let formatted = format(negative_zero);
-        assert!(formatted == "0");
-    }
-
-    #[test]
-    fn zero() {
-        let formatted = format(Decimal::ZERO);
-        assert!(formatted == "0");
-    }
-
-    #[test]
-    fn one() {
-        let formatted = format(Decimal::ONE);
-        assert!(formatted == "1");
-    }
-
-    #[test]
-    fn negative_three_ooooh() {
-        let mut negative_two_dot = Decimal::from_str("3.00000").unwrap();
-        negative_two_dot.set_sign_negative(true);
-
-        let formatted = format(negative_two_dot);
-        assert!(formatted == "-3");
-    }
-
-    #[test]
-    fn negative_three_ooooh_three() {
-        let mut negative_two_dot = Decimal::from_str("3.00003").unwrap();
-        negative_two_dot.set_sign_negative(true);
-
-        let formatted = format(negative_two_dot);
-        assert!(formatted == "-3");
-    }
-
-    #[test]
-    fn negative_pi() {
-        let mut negative_pi = Decimal::PI.clone();
-        negative_pi.set_sign_negative(true);
-
-        let formatted = format(negative_pi);
-        assert!(formatted == "-3.1415");
-    }
-
-    #[test]
-    fn negative_two_dot() {
-        let mut negative_two_dot = Decimal::from_str("2.").unwrap();
-        negative_two_dot.set_sign_negative(true);
-
-        let formatted = format(negative_two_dot);
-        assert!(formatted == "-2");
-    }
-
-    #[test]
-    fn two_dot() {
-        let formatted = format(Decimal::from_str("2.").unwrap());
-        assert!(formatted == "2");
-    }
-
-    #[test]
-    fn pi() {
-        let formatted = format(Decimal::PI);
-        assert!(formatted == "3.1415");
-    }
-
-    #[test]
-    fn three_ooooh_three() {
-        // acid techno!
-        let formatted = format(Decimal::from_str("3.00003").unwrap());
-        assert!(formatted == "3");
-    }
-
-    #[test]
-    fn three_ooooh() {
-        let formatted = format(Decimal::from_str("3.00000").unwrap());
-        assert!(formatted == "3");
-    }
+pub type Warnings = Vec<Warning>;
+
+// An Amount literal, whichever type that is, as dec!() is for Decimal
+#[macro_export]
+macro_rules! amount {
+    ($($amount:tt)+) => {
+        $crate::Amount::from(rust_decimal_macros::dec!($($amount)+))
+    };
 }
@@ -1,34 +1,666 @@
-pub mod account;
-pub mod accounts;
-pub mod libs;
-pub mod transaction;
-pub mod transaction_logs;
+mod options;
+mod serve;
 
-use crate::libs::*;
+use crate::options::*;
+use crate::serve::*;
+use uosckjwncs::*;
 
-use csv::{ReaderBuilder, Trim};
-use rust_decimal::prelude::*;
-use std::collections::{HashMap, HashSet};
+use csv::{ByteRecord, Position, Reader, ReaderBuilder, StringRecord, Trim, WriterBuilder};
+use std::borrow::Cow;
 use std::env;
-use std::fs::File;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::net::TcpListener;
+use std::path::Path;
 use std::process::exit;
 
 fn main() {
-    let mut accounts: Accounts = Accounts::new();
-    let mut disputes: Disputes = HashSet::new();
-    let mut transaction_logs = TransactionLogs::new();
+    let options = Options::parse(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("Error: {err}");
+        eprintln!(
+            "{}",
+            usage(
+                &env::args()
+                    .next()
+                    .unwrap_or_else(|| String::from("uosckjwncs"))
+            )
+        );
+        exit(1)
+    });
+
+    let mut config = options.config.clone();
+
+    if let Some(filename) = &options.fx_rates {
+        for fx_rate in load_records(filename, FxRate::parse_record) {
+            config.fx_rates.add(fx_rate);
+        }
+    }
+
+    let mut ledger = Ledger::with_config(config);
 
-    let filename = env::args().nth(1).unwrap_or_else(|| {
+    if let Some(filename) = &options.load_state {
+        load_state(&mut ledger, filename).unwrap_or_else(|err| {
+            eprintln!("Error: could not load state from '{filename}' ({err})");
+            exit(1)
+        });
+    }
+
+    if let Some(filename) = &options.client_metadata {
+        for client_metadata in load_records(filename, ClientMetadata::parse_record) {
+            ledger.apply_metadata(client_metadata);
+        }
+    }
+
+    let filenames = match &options.command {
+        Command::Process(filenames) => filenames,
+        Command::Serve(address) => {
+            let listener = TcpListener::bind(address).unwrap_or_else(|err| {
+                eprintln!("Error: could not listen on '{address}' ({err})");
+                exit(1)
+            });
+
+            serve(listener, ledger, &options);
+
+            return;
+        }
+    };
+
+    let (mut journal, resume) = match &options.journal {
+        Some(filename) => {
+            let (journal, resume) = open_journal(&mut ledger, filename).unwrap_or_else(|err| {
+                eprintln!("Error: could not recover from journal '{filename}' ({err})");
+                exit(1)
+            });
+
+            (Some(journal), resume)
+        }
+        None => (None, None),
+    };
+
+    if let Some(entry) = resume
+        .as_ref()
+        .filter(|entry| entry.input >= filenames.len())
+    {
         eprintln!(
-            "Usage: {} <filename>",
-            env::args()
-                .next()
-                .unwrap_or_else(|| String::from("uosckjwncs"))
+            "Error: journal stopped in input {} but only {} were given",
+            entry.input + 1,
+            filenames.len()
         );
         exit(1)
+    }
+
+    let mut rejects = options.rejects.as_ref().map(|filename| {
+        let rejects = if let Some(entry) = &resume {
+            // Keep the rows rejected before the previous run died
+            trim_rejects(filename, entry.line).and_then(|_| {
+                OpenOptions::new()
+                    .append(true)
+                    .open(filename)
+                    .map(Rejects::without_header)
+                    .map_err(csv::Error::from)
+            })
+        } else {
+            File::create(filename)
+                .map_err(csv::Error::from)
+                .and_then(Rejects::new)
+        };
+
+        rejects.unwrap_or_else(|err| {
+            eprintln!("Error: could not create '{filename}' ({err})");
+            exit(1)
+        })
+    });
+
+    let ledger = if options.threads > 1 {
+        process_sharded(&options, filenames, ledger, &mut rejects)
+    } else {
+        process(
+            &options,
+            filenames,
+            resume,
+            ledger,
+            &mut journal,
+            &mut rejects,
+        )
+    };
+
+    flush(&mut rejects);
+
+    // Only put in place once the journal is gone, as recovering would replay
+    // the journal onto it a second time
+    if let Some(filename) = &options.save_state {
+        stage_state(&ledger, filename).unwrap_or_else(|err| {
+            eprintln!("Error: could not save state to '{filename}' ({err})");
+            exit(1)
+        });
+    }
+
+    let written = options
+        .compress_output
+        .encoder(io::stdout().lock())
+        .and_then(|mut encoder| {
+            let warnings = write_accounts(
+                &ledger.accounts,
+                options.sort_by,
+                options.output_format,
+                &ledger.config.money,
+                &mut encoder,
+            )?;
+
+            encoder.finish()?.flush()?;
+
+            Ok(warnings)
+        });
+
+    let warnings = written.unwrap_or_else(|err| {
+        eprintln!("Error: could not write balances ({err})");
+        exit(1)
+    });
+
+    for warning in &warnings {
+        eprintln!("Warning: {warning}");
+    }
+
+    if let Some(filename) = &options.journal {
+        // The run is complete, so there is nothing left to recover
+        drop(journal);
+
+        fs::remove_file(filename)
+            .and_then(|_| sync_directory(filename))
+            .unwrap_or_else(|err| {
+                eprintln!("Error: could not remove journal '{filename}' ({err})");
+                exit(1)
+            });
+    }
+
+    // Dying before this leaves the previous snapshot, which the journal (or
+    // without it, the whole input) still applies to
+    if let Some(filename) = &options.save_state {
+        commit_state(filename).unwrap_or_else(|err| {
+            eprintln!("Error: could not save state to '{filename}' ({err})");
+            exit(1)
+        });
+    }
+}
+
+// An input file, or stdin for -, decompressed if need be
+enum Source {
+    File(File),
+    Stdin(Box<dyn Read>),
+    // Seeking decompresses the file again up to the position
+    Decompressed {
+        filename: String,
+        compression: Compression,
+        reader: Box<dyn Read>,
+        byte: u64,
+    },
+}
+
+impl Source {
+    fn open(filename: &str) -> io::Result<Self> {
+        let mut magic = Vec::new();
+
+        if filename == "-" {
+            io::stdin().take(4).read_to_end(&mut magic)?;
+
+            let compression = Compression::detect(&magic, filename);
+            let stdin = Cursor::new(magic).chain(io::stdin());
+
+            return Ok(Source::Stdin(compression.decoder(stdin)?));
+        }
+
+        let mut file = File::open(filename)?;
+
+        (&mut file).take(4).read_to_end(&mut magic)?;
+        file.rewind()?;
+
+        Ok(match Compression::detect(&magic, filename) {
+            Compression::None => Source::File(file),
+            compression => Source::Decompressed {
+                filename: String::from(filename),
+                compression,
+                reader: compression.decoder(file)?,
+                byte: 0,
+            },
+        })
+    }
+}
+
+impl Read for Source {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Source::File(file) => file.read(buf),
+            Source::Stdin(stdin) => stdin.read(buf),
+            Source::Decompressed { reader, byte, .. } => {
+                let read = reader.read(buf)?;
+
+                *byte += read as u64;
+
+                Ok(read)
+            }
+        }
+    }
+}
+
+impl Seek for Source {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match (self, pos) {
+            (Source::File(file), pos) => file.seek(pos),
+            (
+                Source::Decompressed {
+                    filename,
+                    compression,
+                    reader,
+                    byte,
+                },
+                SeekFrom::Start(target),
+            ) => {
+                if target < *byte {
+                    *reader = compression.decoder(File::open(&*filename)?)?;
+                    *byte = 0;
+                }
+
+                *byte += io::copy(&mut reader.take(target - *byte), &mut io::sink())?;
+
+                if *byte < target {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+                }
+
+                Ok(target)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the input can only be read through",
+            )),
+        }
+    }
+}
+
+// One of the inputs, with its lines numbered on from the inputs before it as
+// if they had all been concatenated
+struct Input {
+    index: usize,
+    line_offset: u64,
+    reader: Reader<Source>,
+    columns: Columns,
+}
+
+impl Input {
+    fn open(options: &Options, index: usize, filename: &str, line_offset: u64) -> Self {
+        let source = Source::open(filename).unwrap_or_else(|err| {
+            eprintln!("Error: could not open '{filename}' ({err})");
+            exit(1)
+        });
+
+        let mut reader = options.input_format.reader_builder().from_reader(source);
+
+        // Each input has its own header, so columns may differ between them
+        let columns = match options.input_format {
+            InputFormat::Csv => Columns::from_header(reader.headers().unwrap_or_else(|err| {
+                eprintln!("Error: could not read the header of '{filename}' ({err})");
+                exit(1)
+            })),
+            InputFormat::JsonLines => Columns::Json,
+        };
+
+        Input {
+            index,
+            line_offset,
+            reader,
+            columns,
+        }
+    }
+
+    fn resume(&mut self, filename: &str, entry: &JournalEntry) {
+        let mut position = Position::new();
+
+        position
+            .set_byte(entry.byte)
+            .set_line(entry.line - entry.line_offset)
+            .set_record(entry.record);
+
+        self.reader.seek(position).unwrap_or_else(|err| {
+            eprintln!("Error: could not resume '{filename}' ({err})");
+            exit(1)
+        });
+
+        eprintln!("Resuming '{filename}' at line {}", entry.line);
+    }
+
+    // Where the next input's lines start from
+    fn next_line_offset(&self) -> u64 {
+        self.line_offset + self.reader.position().line() - 1
+    }
+}
+
+fn process(
+    options: &Options,
+    filenames: &[String],
+    resume: Option<JournalEntry>,
+    mut ledger: Ledger,
+    journal: &mut Option<Journal<File>>,
+    rejects: &mut Option<Rejects<File>>,
+) -> Ledger {
+    let (first, mut line_offset) = resume
+        .as_ref()
+        .map(|entry| (entry.input, entry.line_offset))
+        .unwrap_or_default();
+
+    for (index, filename) in filenames.iter().enumerate().skip(first) {
+        let mut input = Input::open(options, index, filename, line_offset);
+        let mut record = ByteRecord::new();
+
+        if let Some(entry) = resume.as_ref().filter(|entry| entry.input == index) {
+            input.resume(filename, entry);
+        }
+
+        loop {
+            let outcome = match input.reader.read_byte_record(&mut record) {
+                Ok(false) => break,
+                Ok(true) => match decode(mem::take(&mut record)) {
+                    Ok(record) => Outcome {
+                        line: line_offset + line(record.position()),
+                        result: input.columns.normalize(&record).and_then(|normalized| {
+                            match journal {
+                                // The reader is now positioned just after the record
+                                Some(journal) => {
+                                    apply_journaled(&mut ledger, &normalized, &input, journal)
+                                }
+                                None => ledger.apply_record(&normalized),
+                            }
+                        }),
+                        warnings: mem::take(&mut ledger.warnings),
+                        record,
+                    },
+                    Err(record) => Outcome {
+                        line: line_offset + line(record.position()),
+                        result: Err(TransactionError::MalformedRecord),
+                        warnings: Warnings::new(),
+                        record,
+                    },
+                },
+                Err(err) if err.is_io_error() => {
+                    flush(rejects);
+                    eprintln!("Error: could not read '{filename}' ({err})");
+                    exit(1)
+                }
+                Err(err) => Outcome {
+                    line: line_offset + line(err.position()),
+                    result: Err(TransactionError::MalformedRecord),
+                    warnings: Warnings::new(),
+                    record: StringRecord::new(),
+                },
+            };
+
+            report(&outcome, &options.config, rejects);
+
+            if journal.is_some() && outcome.result.is_err() {
+                // Rejected rows must survive a crash as well as applied ones
+                sync(rejects);
+            }
+        }
+
+        line_offset = input.next_line_offset();
+    }
+
+    ledger
+}
+
+fn process_sharded(
+    options: &Options,
+    filenames: &[String],
+    ledger: Ledger,
+    rejects: &mut Option<Rejects<File>>,
+) -> Ledger {
+    let mut sharded_ledger =
+        ShardedLedger::from_ledger(ledger, options.threads).unwrap_or_else(|err| {
+            eprintln!("Error: could not start worker threads ({err})");
+            exit(1)
+        });
+
+    let mut outcomes = Vec::new();
+    let mut line_offset = 0;
+
+    'inputs: for (index, filename) in filenames.iter().enumerate() {
+        let mut input = Input::open(options, index, filename, line_offset);
+
+        for result in input.reader.byte_records() {
+            match result.map(decode) {
+                Ok(Ok(record)) => {
+                    let line = line_offset + line(record.position());
+
+                    match input.columns.normalize(&record) {
+                        Ok(Cow::Borrowed(_)) => sharded_ledger.apply_record(line, record),
+                        Ok(Cow::Owned(normalized)) => {
+                            sharded_ledger.apply_normalized(line, &normalized, record)
+                        }
+                        Err(error) => sharded_ledger.reject(line, error, record),
+                    }
+                }
+                Ok(Err(record)) => sharded_ledger.reject(
+                    line_offset + line(record.position()),
+                    TransactionError::MalformedRecord,
+                    record,
+                ),
+                // A damaged compressed input, say, can't be read any further
+                Err(err) if err.is_io_error() => {
+                    eprintln!("Error: could not read '{filename}' ({err})");
+                    exit(1)
+                }
+                Err(err) => sharded_ledger.reject(
+                    line_offset + line(err.position()),
+                    TransactionError::MalformedRecord,
+                    StringRecord::new(),
+                ),
+            }
+
+            let polled = sharded_ledger.poll();
+
+            // Stop reading, the outcomes so far are reported in line order below
+            let abort = polled
+                .iter()
+                .any(|outcome| aborts(outcome, &options.config));
+
+            outcomes.extend(polled);
+
+            if abort {
+                break 'inputs;
+            }
+        }
+
+        line_offset = input.next_line_offset();
+    }
+
+    let (ledger, remaining) = sharded_ledger.finish().unwrap_or_else(|err| {
+        eprintln!("Error: could not collect results from worker threads ({err})");
+        exit(1)
     });
 
-    let file = File::open(&filename).unwrap_or_else(|err| {
+    outcomes.extend(remaining);
+    outcomes.sort_by_key(|outcome| outcome.line);
+
+    for outcome in &outcomes {
+        report(outcome, &options.config, rejects);
+    }
+
+    ledger
+}
+
+fn apply_journaled(
+    ledger: &mut Ledger,
+    record: &StringRecord,
+    input: &Input,
+    journal: &mut Journal<File>,
+) -> Result<(), TransactionError> {
+    let position = input.reader.position();
+
+    let entry = JournalEntry {
+        input: input.index,
+        line_offset: input.line_offset,
+        byte: position.byte(),
+        line: input.line_offset + position.line(),
+        record: position.record(),
+        transaction: Transaction::parse_record(record, &ledger.config.money)?,
+        rejected: false,
+    };
+
+    ledger
+        .apply_journaled(entry, journal)
+        .unwrap_or_else(|err| {
+            eprintln!("Error: could not write to journal ({err})");
+            exit(1)
+        })
+}
+
+fn open_journal(
+    ledger: &mut Ledger,
+    filename: &str,
+) -> Result<(Journal<File>, Option<JournalEntry>), SnapshotError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(filename)?;
+
+    let replay = ledger.replay(BufReader::new(&file))?;
+
+    // Drop a torn last entry rather than appending after it
+    file.set_len(replay.length)?;
+    file.seek(SeekFrom::End(0))?;
+
+    Ok((Journal::new(file), replay.last))
+}
+
+fn trim_rejects(filename: &str, line: u64) -> csv::Result<()> {
+    // Rows from the resume line onwards are about to be rejected again
+    let temporary = format!("{filename}.tmp");
+
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        // The previous run never wrote one, so there is nothing to keep
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(Rejects::new(File::create(filename)?)?.flush()?);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(file);
+
+    let mut writer = WriterBuilder::new().flexible(true).from_path(&temporary)?;
+
+    for result in reader.records() {
+        let row = result?;
+
+        if let Some(Ok(reject_line)) = row.get(0).map(str::parse::<u64>) {
+            if reject_line >= line {
+                break;
+            }
+        }
+
+        writer.write_record(&row)?;
+    }
+
+    writer.flush()?;
+
+    Ok(fs::rename(&temporary, filename)?)
+}
+
+fn load_state(ledger: &mut Ledger, filename: &str) -> Result<(), SnapshotError> {
+    ledger.load(BufReader::new(File::open(filename)?))
+}
+
+pub fn save_state(ledger: &Ledger, filename: &str) -> Result<(), SnapshotError> {
+    stage_state(ledger, filename)?;
+    commit_state(filename)
+}
+
+// Never leave a half written snapshot behind in place of the last one
+fn stage_state(ledger: &Ledger, filename: &str) -> Result<(), SnapshotError> {
+    let mut file = File::create(format!("{filename}.tmp"))?;
+
+    ledger.save(BufWriter::new(&mut file))?;
+
+    Ok(file.sync_all()?)
+}
+
+fn commit_state(filename: &str) -> Result<(), SnapshotError> {
+    fs::rename(format!("{filename}.tmp"), filename)?;
+
+    Ok(sync_directory(filename)?)
+}
+
+// Makes creating, renaming or removing the file durable
+fn sync_directory(filename: &str) -> io::Result<()> {
+    match Path::new(filename).parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => File::open(directory)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+// Rows which aren't UTF-8 can't be parsed, but are still reported with a
+// lossy copy of what was read
+pub fn decode(record: ByteRecord) -> Result<StringRecord, StringRecord> {
+    StringRecord::from_byte_record(record).map_err(|err| {
+        let record = err.into_byte_record();
+        let mut lossy = record
+            .iter()
+            .map(String::from_utf8_lossy)
+            .collect::<StringRecord>();
+
+        lossy.set_position(record.position().cloned());
+        lossy
+    })
+}
+
+pub fn line(position: Option<&Position>) -> u64 {
+    position.map(|p| p.line()).unwrap_or_default()
+}
+
+pub fn aborts(outcome: &Outcome, config: &Config) -> bool {
+    match outcome.result {
+        Err(TransactionError::Overflow) => config.overflow_policy == OverflowPolicy::Abort,
+        // The transaction logs can't be trusted any more
+        Err(TransactionError::Storage(_)) => true,
+        _ => false,
+    }
+}
+
+fn report(outcome: &Outcome, config: &Config, rejects: &mut Option<Rejects<File>>) {
+    let line = outcome.line;
+
+    for warning in &outcome.warnings {
+        eprintln!("Warning: line {line}: {warning}");
+    }
+
+    if let Err(error) = outcome.result {
+        if let Some(rejects) = rejects {
+            rejects
+                .write(line, error, &outcome.record)
+                .unwrap_or_else(|err| {
+                    eprintln!("Error: could not write rejected transaction ({err})");
+                    exit(1)
+                });
+        }
+
+        if aborts(outcome, config) {
+            flush(rejects);
+            eprintln!("Error: line {line}: {error}, aborting");
+            exit(1)
+        }
+    }
+}
+
+// Reads a CSV file given alongside the input, exiting at the first bad row
+fn load_records<T>(
+    filename: &str,
+    parse_record: fn(&StringRecord) -> Result<T, TransactionError>,
+) -> Vec<T> {
+    let file = File::open(filename).unwrap_or_else(|err| {
         eprintln!("Error: could not open '{filename}' ({err})");
         exit(1)
     });
@@ -38,39 +670,39 @@ fn main() {
         .trim(Trim::All)
         .from_reader(file);
 
-    for record in reader.records().flatten() {
-        let transaction = match Transaction::parse_record(&record) {
-            Some(t) => t,
-            None => continue,
-        };
+    let mut records = Vec::new();
 
-        let mut account = match accounts.find_or_create(transaction.client_id) {
-            Some(a) => a,
-            None => continue,
-        };
+    for result in reader.records() {
+        let outcome = result
+            .map_err(|err| (line(err.position()), TransactionError::MalformedRecord))
+            .and_then(|record| parse_record(&record).map_err(|err| (line(record.position()), err)));
 
-        match &record[0] {
-            "deposit" => account.deposit(&transaction, &mut transaction_logs),
-            "withdrawal" => account.withdrawal(&transaction, &mut transaction_logs),
-            "dispute" => account.dispute(&transaction, &mut transaction_logs, &mut disputes),
-            "resolve" => account.resolve(&transaction, &mut transaction_logs, &mut disputes),
-            "chargeback" => account.chargeback(&transaction, &mut transaction_logs, &mut disputes),
-            _ => continue,
+        match outcome {
+            Ok(parsed) => records.push(parsed),
+            Err((line, err)) => {
+                eprintln!("Error: '{filename}' line {line}: {err}");
+                exit(1)
+            }
         }
-
-        accounts.insert(transaction.client_id, account);
     }
 
-    println!("client,available,held,total,locked");
+    records
+}
 
-    for (client_id, account) in accounts.iter() {
-        println!(
-            "{},{},{},{},{}",
-            client_id,
-            format(account.available),
-            format(account.held),
-            format(account.available + account.held),
-            account.locked
-        )
+fn flush(rejects: &mut Option<Rejects<File>>) {
+    if let Some(rejects) = rejects {
+        rejects.flush().unwrap_or_else(|err| {
+            eprintln!("Error: could not write rejected transactions ({err})");
+            exit(1)
+        });
+    }
+}
+
+fn sync(rejects: &mut Option<Rejects<File>>) {
+    if let Some(rejects) = rejects {
+        rejects.sync().unwrap_or_else(|err| {
+            eprintln!("Error: could not write rejected transactions ({err})");
+            exit(1)
+        });
     }
 }
@@ -0,0 +1,78 @@
+use super::*;
+
+use std::io;
+
+pub struct MemoryStore(HashMap<TransactionId, TransactionLog>);
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore(HashMap::new())
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionLogStore for MemoryStore {
+    fn get(&self, transaction_id: TransactionId) -> io::Result<Option<TransactionLog>> {
+        Ok(self.0.get(&transaction_id).copied())
+    }
+
+    fn insert(
+        &mut self,
+        transaction_id: TransactionId,
+        transaction_log: TransactionLog,
+    ) -> io::Result<()> {
+        self.0.insert(transaction_id, transaction_log);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, transaction_id: TransactionId) -> io::Result<()> {
+        self.0.remove(&transaction_id);
+
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = StoreEntry> + '_> {
+        Box::new(self.0.iter().map(|(id, log)| Ok((*id, *log))))
+    }
+}
+
+#[cfg(test)]
+mod test_insert {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut store = MemoryStore::new();
+
+        let transaction_log = TransactionLog {
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            original_amount: None,
+            original_currency: None,
+            state: TransactionState::Processed,
+        };
+
+        assert!(store.insert(1, transaction_log).is_ok());
+        assert!(store.insert(1, transaction_log).is_ok());
+
+        assert!(store.len() == 1);
+        assert!(store.get(1).unwrap() == Some(transaction_log));
+        assert!(store.get(2).unwrap().is_none());
+        assert!(
+            store.iter().map(|entry| entry.unwrap()).collect::<Vec<_>>()
+                == vec![(1, transaction_log)]
+        );
+    }
+}
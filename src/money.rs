@@ -0,0 +1,257 @@
+use super::*;
+
+use rust_decimal::prelude::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Rounding {
+    // Towards zero, dropping the extra places
+    #[default]
+    Truncate,
+    // To the nearest, halves away from zero
+    HalfUp,
+    // To the nearest, halves to the even neighbour (banker's rounding)
+    HalfEven,
+    // Towards negative infinity
+    Floor,
+    // Towards positive infinity
+    Ceiling,
+}
+
+impl Rounding {
+    pub fn parse(rounding: &str) -> Option<Rounding> {
+        match rounding {
+            "truncate" => Some(Rounding::Truncate),
+            "half-up" => Some(Rounding::HalfUp),
+            "half-even" => Some(Rounding::HalfEven),
+            "floor" => Some(Rounding::Floor),
+            "ceiling" => Some(Rounding::Ceiling),
+            _ => None,
+        }
+    }
+
+    fn strategy(&self) -> RoundingStrategy {
+        match self {
+            Rounding::Truncate => RoundingStrategy::ToZero,
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Rounding::Floor => RoundingStrategy::ToNegativeInfinity,
+            Rounding::Ceiling => RoundingStrategy::ToPositiveInfinity,
+        }
+    }
+}
+
+// How many decimal places amounts are held to, and how any further places are
+// rounded away, on input and output alike
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Money {
+    pub precision: u32,
+    pub rounding: Rounding,
+}
+
+impl Money {
+    pub const MAX_PRECISION: u32 = 18;
+
+    pub fn new() -> Self {
+        Money {
+            precision: 4,
+            rounding: Rounding::Truncate,
+        }
+    }
+
+    // Trailing zeros are dropped, and a negative zero comes out as zero
+    pub fn round(&self, amount: Amount) -> Amount {
+        amount
+            .round_dp_with_strategy(self.precision, self.rounding.strategy())
+            .normalize()
+    }
+
+    pub fn format(&self, amount: Amount) -> String {
+        self.round(amount).to_string()
+    }
+}
+
+impl Default for Money {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(Rounding::parse("half-down").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(Rounding::parse("truncate") == Some(Rounding::Truncate));
+        assert!(Rounding::parse("half-up") == Some(Rounding::HalfUp));
+        assert!(Rounding::parse("half-even") == Some(Rounding::HalfEven));
+        assert!(Rounding::parse("floor") == Some(Rounding::Floor));
+        assert!(Rounding::parse("ceiling") == Some(Rounding::Ceiling));
+    }
+}
+
+#[cfg(test)]
+mod test_round {
+    use super::*;
+
+    fn round(precision: u32, rounding: Rounding, amount: Amount) -> Amount {
+        Money {
+            precision,
+            rounding,
+        }
+        .round(amount)
+    }
+
+    #[test]
+    fn rounding() {
+        for (rounding, expected) in [
+            (
+                Rounding::Truncate,
+                [amount!(1.23), amount!(1.24), amount!(-1.23)],
+            ),
+            (
+                Rounding::HalfUp,
+                [amount!(1.24), amount!(1.25), amount!(-1.24)],
+            ),
+            (
+                Rounding::HalfEven,
+                [amount!(1.24), amount!(1.24), amount!(-1.24)],
+            ),
+            (
+                Rounding::Floor,
+                [amount!(1.23), amount!(1.24), amount!(-1.24)],
+            ),
+            (
+                Rounding::Ceiling,
+                [amount!(1.24), amount!(1.25), amount!(-1.23)],
+            ),
+        ] {
+            assert!(round(2, rounding, amount!(1.235)) == expected[0]);
+            assert!(round(2, rounding, amount!(1.245)) == expected[1]);
+            assert!(round(2, rounding, amount!(-1.235)) == expected[2]);
+        }
+    }
+
+    #[test]
+    fn precision() {
+        let amount = amount!(0.123456789123456789123);
+
+        assert!(round(0, Rounding::HalfUp, amount) == amount!(0));
+        assert!(round(8, Rounding::Truncate, amount) == amount!(0.12345678));
+        assert!(round(18, Rounding::Truncate, amount) == amount!(0.123456789123456789));
+    }
+
+    #[test]
+    fn already_rounded() {
+        assert!(round(4, Rounding::Ceiling, amount!(2.5)) == amount!(2.5));
+        assert!(round(4, Rounding::Ceiling, Amount::MAX) == Amount::MAX);
+    }
+}
+
+#[cfg(test)]
+mod test_format {
+    use super::*;
+
+    fn format(amount: Amount) -> String {
+        Money::new().format(amount)
+    }
+
+    #[test]
+    fn negitive_one() {
+        let formatted = format(-Amount::ONE);
+        assert!(formatted == "-1");
+    }
+
+    #[test]
+    fn negative_zero() {
+        let negative_zero = -Amount::ZERO;
+
+        let formatted = format(negative_zero);
+        assert!(formatted == "0");
+    }
+
+    #[test]
+    fn zero() {
+        let formatted = format(Amount::ZERO);
+        assert!(formatted == "0");
+    }
+
+    #[test]
+    fn one() {
+        let formatted = format(Amount::ONE);
+        assert!(formatted == "1");
+    }
+
+    #[test]
+    fn negative_three_ooooh() {
+        let negative_two_dot = -Amount::from_str("3.00000").unwrap();
+
+        let formatted = format(negative_two_dot);
+        assert!(formatted == "-3");
+    }
+
+    #[test]
+    fn negative_three_ooooh_three() {
+        let negative_two_dot = -Amount::from_str("3.00003").unwrap();
+
+        let formatted = format(negative_two_dot);
+        assert!(formatted == "-3");
+    }
+
+    #[test]
+    fn negative_pi() {
+        let negative_pi = -amount!(3.1415926535897932384626433833);
+
+        let formatted = format(negative_pi);
+        assert!(formatted == "-3.1415");
+    }
+
+    #[test]
+    fn negative_two_dot() {
+        let negative_two_dot = -Amount::from_str("2.").unwrap();
+
+        let formatted = format(negative_two_dot);
+        assert!(formatted == "-2");
+    }
+
+    #[test]
+    fn two_dot() {
+        let formatted = format(Amount::from_str("2.").unwrap());
+        assert!(formatted == "2");
+    }
+
+    #[test]
+    fn pi() {
+        let formatted = format(amount!(3.1415926535897932384626433833));
+        assert!(formatted == "3.1415");
+    }
+
+    #[test]
+    fn three_ooooh_three() {
+        // acid techno!
+        let formatted = format(Amount::from_str("3.00003").unwrap());
+        assert!(formatted == "3");
+    }
+
+    #[test]
+    fn three_ooooh() {
+        let formatted = format(Amount::from_str("3.00000").unwrap());
+        assert!(formatted == "3");
+    }
+
+    #[test]
+    fn eight_places() {
+        let money = Money {
+            precision: 8,
+            rounding: Rounding::HalfEven,
+        };
+
+        assert!(money.format(amount!(3.1415926535897932384626433833)) == "3.14159265");
+        assert!(money.format(Amount::from_str("0.000000015").unwrap()) == "0.00000002");
+    }
+}
@@ -0,0 +1,606 @@
+use uosckjwncs::*;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    // Process files in order, - being stdin, and print the balances
+    Process(Vec<String>),
+    // Accept transaction streams on a TCP address
+    Serve(String),
+}
+
+#[derive(PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub rejects: Option<String>,
+    pub client_metadata: Option<String>,
+    pub fx_rates: Option<String>,
+    pub threads: usize,
+    pub load_state: Option<String>,
+    pub save_state: Option<String>,
+    pub journal: Option<String>,
+    pub input_format: InputFormat,
+    pub sort_by: SortBy,
+    pub output_format: OutputFormat,
+    pub compress_output: Compression,
+    pub config: Config,
+}
+
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [options] <filename>...
+       {program} [options] serve <address>
+
+Files are processed in order into the same ledger, and - reads stdin. Inputs
+compressed with gzip, zstd or bzip2 are decompressed as they are read.
+
+Options:
+  --rejects <file>               write rejected input rows to <file>
+  --client-metadata <file>       read client credit limits and base currencies
+                                 from <file>
+  --fx-rates <file>              convert into base currencies at the timestamped
+                                 exchange rates in <file>
+  --threads <n>                  process clients on <n> worker threads (default 1)
+  --load-state <file>            start from the snapshot in <file>
+  --save-state <file>            write a snapshot to <file> when done
+  --journal <file>               journal applied transactions to <file>, and
+                                 recover from it if a previous run died
+  --input-format <format>        read transactions as csv (default), with columns
+                                 found by name in the header, or jsonl
+  --output-format <format>       write the balances as csv (default), json or jsonl
+  --compress-output <method>     compress the balances with gzip, zstd or bzip2
+  --sort-by <column>             order the balances by client (default), total,
+                                 held or locked; ties are always ordered by client
+  --storage <storage>            keep transaction logs in memory (default) or
+                                 on disk with disk:<directory>
+  --deposit-disputes <mode>      hold-funds (default), pending-credit or refuse
+  --withdrawal-disputes <mode>   pending-credit (default), hold-funds or refuse
+  --no-redispute                 refuse disputes of already resolved transactions
+  --dispute-window <n>           only keep the latest <n> deposits and withdrawals
+                                 open to disputes
+  --precision <n>                hold amounts to <n> decimal places, 0 to 18
+                                 (default 4)
+  --rounding <mode>              round extra places by truncate (default),
+                                 half-up, half-even, floor or ceiling
+  --overflow <policy>            saturate (default), reject or abort on overflow
+  --frozen-allow <kinds>         kinds allowed on frozen accounts, comma separated
+                                 or none (default: resolve,chargeback)
+  --locked-allow <kinds>         kinds allowed on locked accounts, comma separated
+                                 or none (default: resolve,chargeback)"
+    )
+}
+
+fn value<I: Iterator<Item = String>>(args: &mut I, option: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("'{option}' requires a value"))
+}
+
+fn dispute_mode<I: Iterator<Item = String>>(
+    args: &mut I,
+    option: &str,
+) -> Result<DisputeMode, String> {
+    let mode = value(args, option)?;
+
+    DisputeMode::parse(&mode).ok_or_else(|| format!("unknown dispute mode '{mode}'"))
+}
+
+fn kinds<I: Iterator<Item = String>>(
+    args: &mut I,
+    option: &str,
+) -> Result<Vec<TransactionKind>, String> {
+    let kinds = value(args, option)?;
+
+    StatusPolicy::parse_kinds(&kinds).ok_or_else(|| format!("invalid transaction types '{kinds}'"))
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut arguments = Vec::new();
+        let mut rejects = None;
+        let mut client_metadata = None;
+        let mut fx_rates = None;
+        let mut threads = 1;
+        let mut load_state = None;
+        let mut save_state = None;
+        let mut journal = None;
+        let mut input_format = InputFormat::Csv;
+        let mut sort_by = SortBy::Client;
+        let mut output_format = OutputFormat::Csv;
+        let mut compress_output = Compression::None;
+        let mut config = Config::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--rejects" => rejects = Some(value(&mut args, &arg)?),
+                "--client-metadata" => client_metadata = Some(value(&mut args, &arg)?),
+                "--fx-rates" => fx_rates = Some(value(&mut args, &arg)?),
+                "--threads" => {
+                    let value = value(&mut args, &arg)?;
+
+                    threads = match value.parse() {
+                        Ok(threads) if threads > 0 => threads,
+                        _ => return Err(format!("invalid number of threads '{value}'")),
+                    }
+                }
+                "--load-state" => load_state = Some(value(&mut args, &arg)?),
+                "--save-state" => save_state = Some(value(&mut args, &arg)?),
+                "--journal" => journal = Some(value(&mut args, &arg)?),
+                "--deposit-disputes" => {
+                    config.dispute_policy.deposit = dispute_mode(&mut args, &arg)?
+                }
+                "--withdrawal-disputes" => {
+                    config.dispute_policy.withdrawal = dispute_mode(&mut args, &arg)?
+                }
+                "--no-redispute" => config.dispute_policy.allow_redispute = false,
+                "--dispute-window" => {
+                    let value = value(&mut args, &arg)?;
+
+                    config.dispute_policy.window = match value.parse() {
+                        Ok(window) if window > 0 => Some(window),
+                        _ => return Err(format!("invalid dispute window '{value}'")),
+                    }
+                }
+                "--precision" => {
+                    let value = value(&mut args, &arg)?;
+
+                    config.money.precision = match value.parse() {
+                        Ok(precision) if precision <= Money::MAX_PRECISION => precision,
+                        _ => return Err(format!("invalid precision '{value}'")),
+                    }
+                }
+                "--rounding" => {
+                    let rounding = value(&mut args, &arg)?;
+
+                    config.money.rounding = Rounding::parse(&rounding)
+                        .ok_or_else(|| format!("unknown rounding mode '{rounding}'"))?
+                }
+                "--overflow" => {
+                    let policy = value(&mut args, &arg)?;
+
+                    config.overflow_policy = OverflowPolicy::parse(&policy)
+                        .ok_or_else(|| format!("unknown overflow policy '{policy}'"))?
+                }
+                "--input-format" => {
+                    let format = value(&mut args, &arg)?;
+
+                    input_format = InputFormat::parse(&format)
+                        .ok_or_else(|| format!("unknown input format '{format}'"))?
+                }
+                "--output-format" => {
+                    let format = value(&mut args, &arg)?;
+
+                    output_format = OutputFormat::parse(&format)
+                        .ok_or_else(|| format!("unknown output format '{format}'"))?
+                }
+                "--compress-output" => {
+                    let compression = value(&mut args, &arg)?;
+
+                    compress_output = Compression::parse(&compression)
+                        .ok_or_else(|| format!("unknown compression '{compression}'"))?
+                }
+                "--sort-by" => {
+                    let column = value(&mut args, &arg)?;
+
+                    sort_by = SortBy::parse(&column)
+                        .ok_or_else(|| format!("unknown sort column '{column}'"))?
+                }
+                "--storage" => {
+                    let storage = value(&mut args, &arg)?;
+
+                    config.storage = Storage::parse(&storage)
+                        .ok_or_else(|| format!("invalid storage '{storage}'"))?
+                }
+                "--frozen-allow" => config.status_policy.frozen = kinds(&mut args, &arg)?,
+                "--locked-allow" => config.status_policy.locked = kinds(&mut args, &arg)?,
+                _ if arg.starts_with("--") => return Err(format!("unknown option '{arg}'")),
+                _ => arguments.push(arg),
+            }
+        }
+
+        let command = match arguments.first() {
+            None => return Err(String::from("missing filename")),
+            Some(command) if command == "serve" => match &arguments[1..] {
+                [] => return Err(String::from("'serve' requires an address")),
+                [address] => Command::Serve(address.clone()),
+                [_, arg, ..] => return Err(format!("unexpected argument '{arg}'")),
+            },
+            Some(_) => Command::Process(arguments),
+        };
+
+        if let Command::Serve(_) = command {
+            // Rejected rows are sent back on each connection instead
+            if rejects.is_some() {
+                return Err(String::from("'--rejects' cannot be used with 'serve'"));
+            }
+
+            if threads > 1 {
+                return Err(String::from("'--threads' cannot be used with 'serve'"));
+            }
+
+            if journal.is_some() {
+                return Err(String::from("'--journal' cannot be used with 'serve'"));
+            }
+
+            if compress_output != Compression::None {
+                return Err(String::from(
+                    "'--compress-output' cannot be used with 'serve'",
+                ));
+            }
+        }
+
+        // Stdin can't be read again to recover
+        if let Command::Process(filenames) = &command {
+            if journal.is_some() && filenames.iter().any(|filename| filename == "-") {
+                return Err(String::from("'--journal' cannot be used with stdin"));
+            }
+        }
+
+        if journal.is_some() && threads > 1 {
+            return Err(String::from("'--journal' cannot be used with '--threads'"));
+        }
+
+        // Each worker would only see its own share of the window
+        if config.dispute_policy.window.is_some() && threads > 1 {
+            return Err(String::from(
+                "'--dispute-window' cannot be used with '--threads'",
+            ));
+        }
+
+        // The owner of every id would still be kept in memory by the
+        // ShardedLedger, which is what the disk storage is there to avoid
+        if matches!(config.storage, Storage::Disk(_)) && threads > 1 {
+            return Err(String::from(
+                "'--storage disk:<directory>' cannot be used with '--threads'",
+            ));
+        }
+
+        Ok(Options {
+            command,
+            rejects,
+            client_metadata,
+            fx_rates,
+            threads,
+            load_state,
+            save_state,
+            journal,
+            input_format,
+            sort_by,
+            output_format,
+            compress_output,
+            config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| String::from(*arg))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn missing_filename() {
+        assert!(Options::parse(args(&[])).is_err());
+        assert!(Options::parse(args(&["--rejects", "rejects.csv"])).is_err());
+    }
+
+    #[test]
+    fn missing_rejects_filename() {
+        assert!(Options::parse(args(&["input.csv", "--rejects"])).is_err());
+    }
+
+    #[test]
+    fn unknown_option() {
+        assert!(Options::parse(args(&["input.csv", "--bogus"])).is_err());
+    }
+
+    #[test]
+    fn several_filenames() {
+        let options = Options::parse(args(&["00.csv", "-", "01.csv", "--threads", "2"])).unwrap();
+
+        assert!(
+            options.command
+                == Command::Process(vec![
+                    String::from("00.csv"),
+                    String::from("-"),
+                    String::from("01.csv"),
+                ])
+        );
+    }
+
+    #[test]
+    fn serve() {
+        let options = Options::parse(args(&["serve", "127.0.0.1:7878", "--no-redispute"]));
+
+        assert!(
+            options
+                == Ok(Options {
+                    command: Command::Serve(String::from("127.0.0.1:7878")),
+                    rejects: None,
+                    client_metadata: None,
+                    fx_rates: None,
+                    threads: 1,
+                    load_state: None,
+                    save_state: None,
+                    journal: None,
+                    input_format: InputFormat::Csv,
+                    sort_by: SortBy::Client,
+                    output_format: OutputFormat::Csv,
+                    compress_output: Compression::None,
+                    config: Config {
+                        dispute_policy: DisputePolicy {
+                            allow_redispute: false,
+                            ..DisputePolicy::new()
+                        },
+                        ..Config::new()
+                    },
+                })
+        );
+    }
+
+    #[test]
+    fn serve_invalid() {
+        assert!(Options::parse(args(&["serve"])).is_err());
+        assert!(Options::parse(args(&["serve", "127.0.0.1:7878", "extra"])).is_err());
+        assert!(Options::parse(args(&["serve", "127.0.0.1:7878", "--rejects", "r.csv"])).is_err());
+        assert!(Options::parse(args(&["serve", "127.0.0.1:7878", "--threads", "4"])).is_err());
+    }
+
+    #[test]
+    fn ok() {
+        let options = Options::parse(args(&["input.csv"]));
+
+        assert!(
+            options
+                == Ok(Options {
+                    command: Command::Process(vec![String::from("input.csv")]),
+                    rejects: None,
+                    client_metadata: None,
+                    fx_rates: None,
+                    threads: 1,
+                    load_state: None,
+                    save_state: None,
+                    journal: None,
+                    input_format: InputFormat::Csv,
+                    sort_by: SortBy::Client,
+                    output_format: OutputFormat::Csv,
+                    compress_output: Compression::None,
+                    config: Config::new(),
+                })
+        );
+    }
+
+    #[test]
+    fn unknown_dispute_mode() {
+        assert!(Options::parse(args(&["input.csv", "--deposit-disputes", "hold"])).is_err());
+    }
+
+    #[test]
+    fn dispute_modes() {
+        let options = Options::parse(args(&[
+            "input.csv",
+            "--deposit-disputes",
+            "refuse",
+            "--withdrawal-disputes",
+            "hold-funds",
+        ]))
+        .unwrap();
+
+        assert!(options.config.dispute_policy.deposit == DisputeMode::Refuse);
+        assert!(options.config.dispute_policy.withdrawal == DisputeMode::HoldFunds);
+        assert!(options.config.dispute_policy.allow_redispute);
+    }
+
+    #[test]
+    fn no_redispute() {
+        let options = Options::parse(args(&["input.csv", "--no-redispute"])).unwrap();
+        assert!(!options.config.dispute_policy.allow_redispute);
+    }
+
+    #[test]
+    fn dispute_window() {
+        let options = Options::parse(args(&["input.csv", "--dispute-window", "1000"])).unwrap();
+        assert!(options.config.dispute_policy.window == Some(1000));
+
+        assert!(Options::parse(args(&["input.csv", "--dispute-window", "0"])).is_err());
+        assert!(Options::parse(args(&[
+            "input.csv",
+            "--dispute-window",
+            "2",
+            "--threads",
+            "2"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn overflow() {
+        let options = Options::parse(args(&["input.csv", "--overflow", "abort"])).unwrap();
+        assert!(options.config.overflow_policy == OverflowPolicy::Abort);
+
+        assert!(Options::parse(args(&["input.csv", "--overflow", "panic"])).is_err());
+    }
+
+    #[test]
+    fn money() {
+        let options = Options::parse(args(&[
+            "input.csv",
+            "--precision",
+            "8",
+            "--rounding",
+            "half-even",
+        ]))
+        .unwrap();
+
+        assert!(
+            options.config.money
+                == Money {
+                    precision: 8,
+                    rounding: Rounding::HalfEven,
+                }
+        );
+
+        assert!(Options::parse(args(&["input.csv", "--precision", "19"])).is_err());
+        assert!(Options::parse(args(&["input.csv", "--precision", "-1"])).is_err());
+        assert!(Options::parse(args(&["input.csv", "--rounding", "up"])).is_err());
+    }
+
+    #[test]
+    fn client_metadata() {
+        let options =
+            Options::parse(args(&["input.csv", "--client-metadata", "clients.csv"])).unwrap();
+
+        assert!(options.client_metadata == Some(String::from("clients.csv")));
+    }
+
+    #[test]
+    fn fx_rates() {
+        let options = Options::parse(args(&["input.csv", "--fx-rates", "rates.csv"])).unwrap();
+
+        assert!(options.fx_rates == Some(String::from("rates.csv")));
+    }
+
+    #[test]
+    fn threads() {
+        let options = Options::parse(args(&["input.csv", "--threads", "8"])).unwrap();
+        assert!(options.threads == 8);
+
+        assert!(Options::parse(args(&["input.csv", "--threads", "0"])).is_err());
+        assert!(Options::parse(args(&["input.csv", "--threads", "many"])).is_err());
+    }
+
+    #[test]
+    fn state() {
+        let options = Options::parse(args(&[
+            "--load-state",
+            "monday.json",
+            "input.csv",
+            "--save-state",
+            "tuesday.json",
+        ]))
+        .unwrap();
+
+        assert!(options.load_state == Some(String::from("monday.json")));
+        assert!(options.save_state == Some(String::from("tuesday.json")));
+
+        assert!(Options::parse(args(&["input.csv", "--save-state"])).is_err());
+    }
+
+    #[test]
+    fn journal() {
+        let options = Options::parse(args(&["input.csv", "--journal", "input.journal"])).unwrap();
+        assert!(options.journal == Some(String::from("input.journal")));
+
+        assert!(Options::parse(args(&["input.csv", "--journal", "j", "--threads", "2"])).is_err());
+        assert!(Options::parse(args(&["serve", "127.0.0.1:7878", "--journal", "j"])).is_err());
+        assert!(Options::parse(args(&["input.csv", "-", "--journal", "j"])).is_err());
+    }
+
+    #[test]
+    fn input_format() {
+        let options = Options::parse(args(&["input.jsonl", "--input-format", "jsonl"])).unwrap();
+        assert!(options.input_format == InputFormat::JsonLines);
+
+        assert!(Options::parse(args(&["input.csv", "--input-format", "xml"])).is_err());
+    }
+
+    #[test]
+    fn output_format() {
+        let options = Options::parse(args(&["input.csv", "--output-format", "jsonl"])).unwrap();
+        assert!(options.output_format == OutputFormat::JsonLines);
+
+        assert!(Options::parse(args(&["input.csv", "--output-format", "xml"])).is_err());
+    }
+
+    #[test]
+    fn compress_output() {
+        let options = Options::parse(args(&["input.csv", "--compress-output", "zstd"])).unwrap();
+        assert!(options.compress_output == Compression::Zstd);
+
+        assert!(Options::parse(args(&["input.csv", "--compress-output", "lz4"])).is_err());
+        assert!(Options::parse(args(&[
+            "serve",
+            "127.0.0.1:7878",
+            "--compress-output",
+            "gzip"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn sort_by() {
+        let options = Options::parse(args(&["input.csv", "--sort-by", "total"])).unwrap();
+        assert!(options.sort_by == SortBy::Total);
+
+        assert!(Options::parse(args(&["input.csv", "--sort-by", "available"])).is_err());
+    }
+
+    #[test]
+    fn storage() {
+        let options = Options::parse(args(&["input.csv", "--storage", "disk:/var/tmp"])).unwrap();
+        assert!(options.config.storage == Storage::Disk(PathBuf::from("/var/tmp")));
+
+        assert!(Options::parse(args(&["input.csv", "--storage", "tape"])).is_err());
+        assert!(Options::parse(args(&[
+            "input.csv",
+            "--storage",
+            "disk:/tmp",
+            "--threads",
+            "2"
+        ]))
+        .is_err());
+        assert!(Options::parse(args(&["input.csv", "--storage"])).is_err());
+    }
+
+    #[test]
+    fn status_policy() {
+        let options = Options::parse(args(&[
+            "input.csv",
+            "--frozen-allow",
+            "deposit,resolve",
+            "--locked-allow",
+            "none",
+        ]))
+        .unwrap();
+
+        assert!(
+            options.config.status_policy
+                == StatusPolicy {
+                    frozen: vec![TransactionKind::Deposit, TransactionKind::Resolve],
+                    locked: Vec::new(),
+                }
+        );
+
+        assert!(Options::parse(args(&["input.csv", "--locked-allow", "unlock"])).is_err());
+        assert!(Options::parse(args(&["input.csv", "--frozen-allow"])).is_err());
+    }
+
+    #[test]
+    fn rejects() {
+        let options = Options::parse(args(&["--rejects", "rejects.csv", "input.csv"]));
+
+        assert!(
+            options
+                == Ok(Options {
+                    command: Command::Process(vec![String::from("input.csv")]),
+                    rejects: Some(String::from("rejects.csv")),
+                    client_metadata: None,
+                    fx_rates: None,
+                    threads: 1,
+                    load_state: None,
+                    save_state: None,
+                    journal: None,
+                    input_format: InputFormat::Csv,
+                    sort_by: SortBy::Client,
+                    output_format: OutputFormat::Csv,
+                    compress_output: Compression::None,
+                    config: Config::new(),
+                })
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use super::*;
+
+use csv::StringRecord;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub line: u64,
+    pub result: Result<(), TransactionError>,
+    pub warnings: Warnings,
+    pub record: StringRecord,
+}
+
+impl Outcome {
+    pub fn is_reportable(&self) -> bool {
+        self.result.is_err() || !self.warnings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test_is_reportable {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut outcome = Outcome {
+            line: 2,
+            result: Ok(()),
+            warnings: Warnings::new(),
+            record: StringRecord::new(),
+        };
+
+        assert!(!outcome.is_reportable());
+
+        outcome.warnings.push(Warning::Saturated {
+            transaction_id: 1,
+            client_id: 1,
+        });
+
+        assert!(outcome.is_reportable());
+
+        outcome.warnings.clear();
+        outcome.result = Err(TransactionError::InsufficientFunds);
+
+        assert!(outcome.is_reportable());
+    }
+}
@@ -0,0 +1,159 @@
+use super::*;
+
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    // A single JSON array of balances
+    Json,
+    // One JSON object per line
+    JsonLines,
+}
+
+impl OutputFormat {
+    pub fn parse(format: &str) -> Option<OutputFormat> {
+        match format {
+            "csv" => Some(OutputFormat::Csv),
+            "json" => Some(OutputFormat::Json),
+            "jsonl" => Some(OutputFormat::JsonLines),
+            _ => None,
+        }
+    }
+
+    // Whether any of the balances has a currency, as CSV only has a column for
+    // it if so
+    pub fn writer<'a, W: Write + 'a>(
+        &self,
+        writer: W,
+        currencies: bool,
+    ) -> io::Result<Box<dyn BalanceWriter + 'a>> {
+        Ok(match self {
+            OutputFormat::Csv => Box::new(CsvBalances::new(writer, currencies)?),
+            OutputFormat::Json => Box::new(JsonBalances::new(writer)?),
+            OutputFormat::JsonLines => Box::new(JsonLinesBalances::new(writer)),
+        })
+    }
+}
+
+// Writes the balances in the given order and format, returning the warnings
+// about them
+pub fn write_accounts<W: Write>(
+    accounts: &Accounts,
+    sort_by: SortBy,
+    output_format: OutputFormat,
+    money: &Money,
+    writer: W,
+) -> io::Result<Warnings> {
+    let rows = accounts.sorted(sort_by);
+    let mut warnings = Warnings::new();
+    let currencies = rows.iter().any(|(_, _, wallet)| wallet.currency.is_some());
+
+    let mut balances = output_format.writer(writer, currencies)?;
+
+    for (client_id, account, wallet) in rows {
+        balances.write(&Balance::new(
+            client_id,
+            account,
+            &wallet,
+            money,
+            &mut warnings,
+        ))?;
+    }
+
+    balances.finish()?;
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(OutputFormat::parse("xml").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(OutputFormat::parse("csv") == Some(OutputFormat::Csv));
+        assert!(OutputFormat::parse("json") == Some(OutputFormat::Json));
+        assert!(OutputFormat::parse("jsonl") == Some(OutputFormat::JsonLines));
+    }
+}
+
+#[cfg(test)]
+mod test_write_accounts {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut accounts = Accounts::new();
+
+        for client_id in [2, 1] {
+            let mut account = accounts.find_or_create(client_id);
+
+            account.set_wallet(Wallet {
+                available: amount!(1.0),
+                ..Wallet::new(None)
+            });
+
+            accounts.insert(client_id, account);
+        }
+
+        let mut output = Vec::new();
+
+        assert!(write_accounts(
+            &accounts,
+            SortBy::Client,
+            OutputFormat::JsonLines,
+            &Money::new(),
+            &mut output
+        )
+        .is_ok());
+
+        assert!(
+            String::from_utf8(output).unwrap()
+                == concat!(
+                    r#"{"client":1,"available":"1","held":"0","total":"1","locked":false,"debt":"0"}"#,
+                    "\n",
+                    r#"{"client":2,"available":"1","held":"0","total":"1","locked":false,"debt":"0"}"#,
+                    "\n"
+                )
+        );
+    }
+
+    #[test]
+    fn currencies() {
+        let mut accounts = Accounts::new();
+        let mut account = accounts.find_or_create(1);
+
+        account.set_wallet(Wallet {
+            available: amount!(2.5),
+            ..Wallet::new(Currency::parse("EUR"))
+        });
+
+        accounts.insert(1, account);
+        accounts.find_or_create(2);
+
+        let mut output = Vec::new();
+
+        assert!(write_accounts(
+            &accounts,
+            SortBy::Client,
+            OutputFormat::Csv,
+            &Money::new(),
+            &mut output
+        )
+        .is_ok());
+
+        assert!(
+            output
+                == b"client,currency,available,held,total,locked,debt\n\
+                     1,EUR,2.5,0,2.5,false,0\n\
+                     2,,0,0,0,false,0\n"
+        );
+    }
+}
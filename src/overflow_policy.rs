@@ -0,0 +1,203 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    Saturate,
+    Reject,
+    Abort,
+}
+
+impl OverflowPolicy {
+    pub fn parse(policy: &str) -> Option<OverflowPolicy> {
+        match policy {
+            "saturate" => Some(OverflowPolicy::Saturate),
+            "reject" => Some(OverflowPolicy::Reject),
+            "abort" => Some(OverflowPolicy::Abort),
+            _ => None,
+        }
+    }
+
+    pub fn add(
+        &self,
+        a: Amount,
+        b: Amount,
+        transaction: &Transaction,
+        warnings: &mut Warnings,
+    ) -> Result<Amount, TransactionError> {
+        self.resolve(a.checked_add(b), a.saturating_add(b), transaction, warnings)
+    }
+
+    pub fn sub(
+        &self,
+        a: Amount,
+        b: Amount,
+        transaction: &Transaction,
+        warnings: &mut Warnings,
+    ) -> Result<Amount, TransactionError> {
+        self.resolve(a.checked_sub(b), a.saturating_sub(b), transaction, warnings)
+    }
+
+    fn resolve(
+        &self,
+        checked: Option<Amount>,
+        saturated: Amount,
+        transaction: &Transaction,
+        warnings: &mut Warnings,
+    ) -> Result<Amount, TransactionError> {
+        match (checked, self) {
+            (Some(amount), _) => Ok(amount),
+            (None, OverflowPolicy::Saturate) => {
+                warnings.push(Warning::Saturated {
+                    transaction_id: transaction.id,
+                    client_id: transaction.client_id,
+                });
+
+                Ok(saturated)
+            }
+            // Aborting the whole run is left up to the caller
+            (None, OverflowPolicy::Reject | OverflowPolicy::Abort) => {
+                Err(TransactionError::Overflow)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(OverflowPolicy::parse("panic").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(OverflowPolicy::parse("saturate") == Some(OverflowPolicy::Saturate));
+        assert!(OverflowPolicy::parse("reject") == Some(OverflowPolicy::Reject));
+        assert!(OverflowPolicy::parse("abort") == Some(OverflowPolicy::Abort));
+    }
+}
+
+#[cfg(test)]
+mod test_add {
+    use super::*;
+
+    fn transaction() -> Transaction {
+        Transaction {
+            id: 3,
+            client_id: 2,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn ok() {
+        let mut warnings = Warnings::new();
+
+        for policy in [
+            OverflowPolicy::Saturate,
+            OverflowPolicy::Reject,
+            OverflowPolicy::Abort,
+        ] {
+            let result = policy.add(amount!(1.5), amount!(2.0), &transaction(), &mut warnings);
+            assert!(result == Ok(amount!(3.5)));
+        }
+
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn saturate() {
+        let mut warnings = Warnings::new();
+
+        let result =
+            OverflowPolicy::Saturate.add(Amount::MAX, amount!(1.0), &transaction(), &mut warnings);
+
+        assert!(result == Ok(Amount::MAX));
+
+        assert!(
+            warnings
+                == vec![Warning::Saturated {
+                    transaction_id: 3,
+                    client_id: 2,
+                }]
+        );
+    }
+
+    #[test]
+    fn reject() {
+        let mut warnings = Warnings::new();
+
+        let result =
+            OverflowPolicy::Reject.add(Amount::MAX, amount!(1.0), &transaction(), &mut warnings);
+
+        assert!(result == Err(TransactionError::Overflow));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn abort() {
+        let mut warnings = Warnings::new();
+
+        let result =
+            OverflowPolicy::Abort.add(Amount::MAX, amount!(1.0), &transaction(), &mut warnings);
+
+        assert!(result == Err(TransactionError::Overflow));
+        assert!(warnings.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod test_sub {
+    use super::*;
+
+    fn transaction() -> Transaction {
+        Transaction {
+            id: 3,
+            client_id: 2,
+            kind: TransactionKind::Withdrawal,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn ok() {
+        let mut warnings = Warnings::new();
+
+        let result =
+            OverflowPolicy::Reject.sub(amount!(1.5), amount!(2.0), &transaction(), &mut warnings);
+
+        assert!(result == Ok(amount!(-0.5)));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn saturate() {
+        let mut warnings = Warnings::new();
+
+        let result =
+            OverflowPolicy::Saturate.sub(Amount::MIN, amount!(1.0), &transaction(), &mut warnings);
+
+        assert!(result == Ok(Amount::MIN));
+        assert!(warnings.len() == 1);
+    }
+
+    #[test]
+    fn reject() {
+        let mut warnings = Warnings::new();
+
+        let result =
+            OverflowPolicy::Reject.sub(Amount::MIN, amount!(1.0), &transaction(), &mut warnings);
+
+        assert!(result == Err(TransactionError::Overflow));
+        assert!(warnings.is_empty());
+    }
+}
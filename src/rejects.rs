@@ -0,0 +1,147 @@
+use super::*;
+
+use csv::{StringRecord, Terminator, Writer, WriterBuilder};
+use std::io::Write;
+
+pub struct Rejects<W: Write>(Writer<W>);
+
+impl<W: Write> Rejects<W> {
+    pub fn new(writer: W) -> csv::Result<Self> {
+        let mut rejects = Self::without_header(writer);
+
+        rejects.0.write_record(["line", "reason", "record"])?;
+
+        Ok(rejects)
+    }
+
+    // For appending to an existing report
+    pub fn without_header(writer: W) -> Self {
+        Rejects(WriterBuilder::new().flexible(true).from_writer(writer))
+    }
+
+    pub fn write(
+        &mut self,
+        line: u64,
+        error: TransactionError,
+        record: &StringRecord,
+    ) -> csv::Result<()> {
+        let line = line.to_string();
+
+        self.0
+            .write_record([line.as_str(), error.code(), &raw(record)?])
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+
+    pub fn into_inner(self) -> Option<W> {
+        self.0.into_inner().ok()
+    }
+}
+
+// The row as one CSV line, kept whole in a single column as inputs don't all
+// share the same layout
+fn raw(record: &StringRecord) -> csv::Result<String> {
+    // Such as a JSON line, which already is the line as it was read
+    if record.len() <= 1 {
+        return Ok(record.get(0).unwrap_or_default().to_string());
+    }
+
+    let mut writer = WriterBuilder::new()
+        .terminator(Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+
+    writer.write_record(record)?;
+
+    let mut raw = writer.into_inner().map_err(|err| err.into_error())?;
+    raw.pop();
+
+    // Always UTF-8, as it was written from a StringRecord
+    Ok(String::from_utf8(raw).unwrap_or_default())
+}
+
+impl<W: Durable> Rejects<W> {
+    pub fn sync(&mut self) -> std::io::Result<()> {
+        self.0.flush()?;
+        self.0.get_ref().sync()
+    }
+}
+
+#[cfg(test)]
+mod test_new {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let rejects = Rejects::new(Vec::new()).unwrap();
+        let output = rejects.into_inner().unwrap();
+
+        assert!(output == b"line,reason,record\n");
+    }
+}
+
+#[cfg(test)]
+mod test_without_header {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let rejects = Rejects::without_header(Vec::new());
+        let output = rejects.into_inner().unwrap();
+
+        assert!(output.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod test_write {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let mut rejects = Rejects::new(Vec::new()).unwrap();
+
+        //
+        // action
+        //
+
+        rejects
+            .write(
+                2,
+                TransactionError::InsufficientFunds,
+                &StringRecord::from(vec!["withdrawal", "1", "1", "1.0"]),
+            )
+            .unwrap();
+
+        rejects
+            .write(3, TransactionError::MalformedRecord, &StringRecord::new())
+            .unwrap();
+
+        rejects
+            .write(
+                4,
+                TransactionError::MissingColumns,
+                &StringRecord::from(vec![r#"{"type":"deposit"}"#]),
+            )
+            .unwrap();
+
+        //
+        // test what we expect
+        //
+
+        let output = rejects.into_inner().unwrap();
+
+        assert!(
+            output
+                == b"line,reason,record\n\
+                     2,insufficient_funds,\"withdrawal,1,1,1.0\"\n\
+                     3,malformed_record,\n\
+                     4,missing_columns,\"{\"\"type\"\":\"\"deposit\"\"}\"\n"
+        );
+    }
+}
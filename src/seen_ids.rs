@@ -0,0 +1,179 @@
+use super::*;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Ids are tracked in pages of bits, so a dense run of ids costs a bit each
+// while sparse ids only pay for the pages they touch
+const PAGE_BITS: u32 = 1 << 16;
+const PAGE_WORDS: usize = PAGE_BITS as usize / 64;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SeenIds {
+    pages: HashMap<u32, Box<[u64; PAGE_WORDS]>>,
+    len: usize,
+}
+
+impl SeenIds {
+    pub fn new() -> Self {
+        SeenIds {
+            pages: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, transaction_id: TransactionId) -> bool {
+        let page = self
+            .pages
+            .entry(transaction_id / PAGE_BITS)
+            .or_insert_with(|| Box::new([0; PAGE_WORDS]));
+
+        let (word, bit) = position(transaction_id);
+        let is_new = page[word] & bit == 0;
+
+        page[word] |= bit;
+
+        if is_new {
+            self.len += 1;
+        }
+
+        is_new
+    }
+
+    pub fn contains(&self, transaction_id: TransactionId) -> bool {
+        let (word, bit) = position(transaction_id);
+
+        self.pages
+            .get(&(transaction_id / PAGE_BITS))
+            .map(|page| page[word] & bit != 0)
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Runs of consecutive ids as inclusive ranges, in order
+    pub fn ranges(&self) -> Vec<(TransactionId, TransactionId)> {
+        let mut pages = self.pages.iter().collect::<Vec<_>>();
+        let mut ranges: Vec<(TransactionId, TransactionId)> = Vec::new();
+
+        pages.sort_by_key(|(index, _)| **index);
+
+        for (index, page) in pages {
+            for (word, bits) in page.iter().enumerate() {
+                for bit in 0..64 {
+                    if bits & (1 << bit) == 0 {
+                        continue;
+                    }
+
+                    let transaction_id = index * PAGE_BITS + word as u32 * 64 + bit;
+
+                    match ranges.last_mut() {
+                        Some((_, end)) if *end + 1 == transaction_id => *end = transaction_id,
+                        _ => ranges.push((transaction_id, transaction_id)),
+                    }
+                }
+            }
+        }
+
+        ranges
+    }
+}
+
+fn position(transaction_id: TransactionId) -> (usize, u64) {
+    let offset = transaction_id % PAGE_BITS;
+
+    (offset as usize / 64, 1 << (offset % 64))
+}
+
+impl Serialize for SeenIds {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.ranges().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SeenIds {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut seen_ids = SeenIds::new();
+
+        for (start, end) in Vec::<(TransactionId, TransactionId)>::deserialize(deserializer)? {
+            for transaction_id in start..=end {
+                seen_ids.insert(transaction_id);
+            }
+        }
+
+        Ok(seen_ids)
+    }
+}
+
+#[cfg(test)]
+mod test_insert {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut seen_ids = SeenIds::new();
+
+        assert!(seen_ids.insert(1));
+        assert!(seen_ids.insert(u32::MAX));
+        assert!(!seen_ids.insert(1));
+
+        assert!(seen_ids.len() == 2);
+        assert!(seen_ids.contains(1));
+        assert!(seen_ids.contains(u32::MAX));
+        assert!(!seen_ids.contains(2));
+        assert!(!seen_ids.contains(PAGE_BITS + 1));
+    }
+}
+
+#[cfg(test)]
+mod test_ranges {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert!(SeenIds::new().ranges().is_empty());
+    }
+
+    #[test]
+    fn ok() {
+        let mut seen_ids = SeenIds::new();
+
+        for transaction_id in [7, 3, 4, 5, PAGE_BITS - 1, PAGE_BITS, u32::MAX] {
+            seen_ids.insert(transaction_id);
+        }
+
+        assert!(
+            seen_ids.ranges()
+                == vec![
+                    (3, 5),
+                    (7, 7),
+                    (PAGE_BITS - 1, PAGE_BITS),
+                    (u32::MAX, u32::MAX)
+                ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_serialize {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut seen_ids = SeenIds::new();
+
+        for transaction_id in [1, 2, 3, 10] {
+            seen_ids.insert(transaction_id);
+        }
+
+        let json = serde_json::to_string(&seen_ids).unwrap();
+
+        assert!(json == "[[1,3],[10,10]]");
+        assert!(serde_json::from_str::<SeenIds>(&json).unwrap() == seen_ids);
+    }
+}
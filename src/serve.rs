@@ -0,0 +1,387 @@
+use crate::*;
+
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+// Connections beyond this are turned away rather than each getting a thread
+const MAX_CONNECTIONS: usize = 64;
+
+// An idle client doesn't keep its thread forever
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+// The CSV header, a command or the first JSON line
+const MAX_FIRST_LINE: u64 = 64 * 1024;
+
+pub fn serve(listener: TcpListener, ledger: Ledger, options: &Options) {
+    let ledger = Arc::new(Mutex::new(ledger));
+    let active = Arc::new(AtomicUsize::new(0));
+
+    if let Ok(address) = listener.local_addr() {
+        eprintln!("Listening on {address}");
+    }
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Warning: could not accept connection ({err})");
+                continue;
+            }
+        };
+
+        // Only this thread takes slots, so the count can't grow in between
+        if active.load(Ordering::SeqCst) >= MAX_CONNECTIONS {
+            eprintln!("Warning: too many connections, dropping one");
+            let _ = writeln!(stream, "error: too many connections");
+            continue;
+        }
+
+        if let Err(err) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+            eprintln!("Warning: could not set a read timeout, dropping connection ({err})");
+            continue;
+        }
+
+        let slot = Slot::take(&active);
+
+        let ledger = Arc::clone(&ledger);
+        let connection = Connection {
+            state_file: options.save_state.clone(),
+            input_format: options.input_format,
+            sort_by: options.sort_by,
+            output_format: options.output_format,
+        };
+
+        // On failure the closure, and with it the stream and slot, is dropped
+        let spawned = thread::Builder::new().spawn(move || {
+            let _slot = slot;
+            let peer = match stream.peer_addr() {
+                Ok(peer) => peer.to_string(),
+                Err(_) => String::from("unknown peer"),
+            };
+
+            if let Err(err) = handle(stream, &peer, &ledger, &connection) {
+                eprintln!("Warning: {peer}: {err}");
+            }
+        });
+
+        if let Err(err) = spawned {
+            eprintln!("Warning: could not start a thread, dropping connection ({err})");
+        }
+    }
+}
+
+// One of the MAX_CONNECTIONS, given back when the connection's thread ends
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(active: &Arc<AtomicUsize>) -> Self {
+        active.fetch_add(1, Ordering::SeqCst);
+
+        Slot(Arc::clone(active))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// What each connection needs from the options
+struct Connection {
+    state_file: Option<String>,
+    input_format: InputFormat,
+    sort_by: SortBy,
+    output_format: OutputFormat,
+}
+
+fn lock(ledger: &Mutex<Ledger>) -> MutexGuard<'_, Ledger> {
+    // Ledger::apply leaves the ledger untouched unless it succeeds
+    ledger.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn handle(
+    mut stream: TcpStream,
+    peer: &str,
+    ledger: &Mutex<Ledger>,
+    connection: &Connection,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut first_line = String::new();
+
+    (&mut reader)
+        .take(MAX_FIRST_LINE)
+        .read_line(&mut first_line)?;
+
+    if first_line.len() as u64 == MAX_FIRST_LINE && !first_line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "first line is too long",
+        ));
+    }
+
+    if first_line.trim() == "dump" {
+        // Don't hold the lock while writing to a possibly slow client
+        let (accounts, money) = {
+            let ledger = lock(ledger);
+
+            (ledger.accounts.clone(), ledger.config.money)
+        };
+
+        let warnings = write_accounts(
+            &accounts,
+            connection.sort_by,
+            connection.output_format,
+            &money,
+            stream,
+        )?;
+
+        for warning in &warnings {
+            eprintln!("Warning: {peer}: {warning}");
+        }
+
+        return Ok(());
+    }
+
+    if first_line.trim() == "save" {
+        let response = match &connection.state_file {
+            Some(filename) => {
+                // The transaction logs may be on disk, so save under the lock
+                // rather than copying the ledger out
+                match save_state(&lock(ledger), filename) {
+                    Ok(()) => String::from("ok"),
+                    Err(err) => format!("error: {err}"),
+                }
+            }
+            None => String::from("error: no --save-state file was given"),
+        };
+
+        return writeln!(stream, "{response}");
+    }
+
+    let mut rejects = Rejects::new(stream)?;
+
+    let mut records = connection
+        .input_format
+        .reader_builder()
+        .from_reader(Cursor::new(first_line).chain(reader));
+
+    let columns = match connection.input_format {
+        InputFormat::Csv => Columns::from_header(records.headers()?),
+        InputFormat::JsonLines => Columns::Json,
+    };
+
+    for result in records.byte_records() {
+        let outcome = match result.map(decode) {
+            Ok(Ok(record)) => {
+                let mut ledger = lock(ledger);
+
+                Outcome {
+                    line: line(record.position()),
+                    result: columns
+                        .normalize(&record)
+                        .and_then(|normalized| ledger.apply_record(&normalized)),
+                    warnings: mem::take(&mut ledger.warnings),
+                    record,
+                }
+            }
+            Ok(Err(record)) => Outcome {
+                line: line(record.position()),
+                result: Err(TransactionError::MalformedRecord),
+                warnings: Warnings::new(),
+                record,
+            },
+            Err(err) if err.is_io_error() => return Err(err.into()),
+            Err(err) => Outcome {
+                line: line(err.position()),
+                result: Err(TransactionError::MalformedRecord),
+                warnings: Warnings::new(),
+                record: StringRecord::new(),
+            },
+        };
+
+        let line = outcome.line;
+
+        for warning in &outcome.warnings {
+            eprintln!("Warning: {peer} line {line}: {warning}");
+        }
+
+        if let Err(error) = outcome.result {
+            rejects.write(line, error, &outcome.record)?;
+            rejects.flush()?;
+
+            if aborts(&outcome, &lock(ledger).config) {
+                eprintln!("Warning: {peer} line {line}: {error}, closing connection");
+                break;
+            }
+        }
+    }
+
+    rejects.flush()
+}
+
+#[cfg(test)]
+mod test_serve {
+    use super::*;
+
+    use std::net::Shutdown;
+
+    fn request(address: &str, body: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+
+        stream.write_all(body).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+
+        stream.read_to_string(&mut response).unwrap();
+
+        response
+    }
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let options = Options::parse([String::from("serve"), address.clone()].into_iter()).unwrap();
+
+        thread::spawn(move || serve(listener, Ledger::new(), &options));
+
+        //
+        // action
+        //
+
+        let streams = (1..=4)
+            .map(|client| {
+                let address = address.clone();
+
+                thread::spawn(move || {
+                    let mut body = String::from("type,client,tx,amount\n");
+
+                    for n in 0..25 {
+                        let tx = client * 100 + n;
+                        body.push_str(&format!("deposit,{client},{tx},1.0\n"));
+                    }
+
+                    body.push_str(&format!(
+                        "withdrawal,{client},{},100.0\n",
+                        client * 100 + 99
+                    ));
+
+                    request(&address, body.as_bytes())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let responses = streams
+            .into_iter()
+            .map(|stream| stream.join().unwrap())
+            .collect::<Vec<_>>();
+
+        let dump = request(&address, b"dump\n");
+        let save = request(&address, b"save\n");
+
+        //
+        // test what we expect
+        //
+
+        for (client, response) in (1..=4).zip(responses) {
+            assert!(
+                response
+                    == format!(
+                        "line,reason,record\n\
+                         27,insufficient_funds,\"withdrawal,{client},{},100.0\"\n",
+                        client * 100 + 99
+                    )
+            );
+        }
+
+        assert!(save == "error: no --save-state file was given\n");
+
+        let mut lines = dump.lines().collect::<Vec<_>>();
+
+        lines.sort();
+
+        assert!(
+            lines
+                == vec![
+                    "1,25,0,25,false,0",
+                    "2,25,0,25,false,0",
+                    "3,25,0,25,false,0",
+                    "4,25,0,25,false,0",
+                    "client,available,held,total,locked,debt",
+                ]
+        );
+    }
+
+    #[test]
+    fn long_first_line() {
+        //
+        // setup
+        //
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let options = Options::parse([String::from("serve"), address.clone()].into_iter()).unwrap();
+
+        thread::spawn(move || serve(listener, Ledger::new(), &options));
+
+        //
+        // action
+        //
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+
+        // The server may close before all of it was sent
+        let _ = stream.write_all(&vec![b'a'; MAX_FIRST_LINE as usize + 1]);
+
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+
+        let dump = request(&address, b"dump\n");
+
+        //
+        // test what we expect
+        //
+
+        assert!(response.is_empty());
+        assert!(dump == "client,available,held,total,locked,debt\n");
+    }
+
+    #[test]
+    fn raw_rejects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let options = Options::parse([String::from("serve"), address.clone()].into_iter()).unwrap();
+
+        thread::spawn(move || serve(listener, Ledger::new(), &options));
+
+        let response = request(
+            &address,
+            b"partner_ref,type,client,tx,amount\n\
+              P-1,withdrawal,1,1,5.0\n\
+              P-2,deposit,1,2,1\xff.0\n",
+        );
+
+        // Both whole as they were read, the one which isn't UTF-8 as near as
+        // can be, rather than under headers of another layout
+        assert!(
+            response
+                == "line,reason,record\n\
+                    2,insufficient_funds,\"P-1,withdrawal,1,1,5.0\"\n\
+                    3,malformed_record,\"P-2,deposit,1,2,1\u{fffd}.0\"\n"
+        );
+    }
+}
@@ -0,0 +1,623 @@
+use super::*;
+
+use csv::StringRecord;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+
+// Messages are sent to the shards in batches to keep channel overhead down
+const BATCH_SIZE: usize = 1024;
+
+// How many batches may be queued for a shard before the dispatcher blocks
+const SHARD_QUEUE: usize = 16;
+
+enum Message {
+    Apply {
+        line: u64,
+        transaction: Transaction,
+        record: StringRecord,
+        // The log of the same transaction id held by another shard, if any
+        foreign: Option<TransactionLog>,
+    },
+    Metadata(ClientMetadata),
+    Find(
+        TransactionId,
+        SyncSender<Result<Option<TransactionLog>, TransactionError>>,
+    ),
+}
+
+struct Shard {
+    sender: SyncSender<Vec<Message>>,
+    batch: Vec<Message>,
+    handle: JoinHandle<Ledger>,
+}
+
+pub struct ShardedLedger {
+    config: Config,
+    shards: Vec<Shard>,
+    // First client to use each transaction id, as seen by the dispatcher
+    owners: HashMap<TransactionId, ClientId>,
+    // Ids evicted before the ledger was sharded, which can never be reused
+    evicted: SeenIds,
+    outcomes: Receiver<Outcome>,
+    rejected: Vec<Outcome>,
+}
+
+impl Shard {
+    fn flush(&mut self) {
+        if !self.batch.is_empty() {
+            let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(BATCH_SIZE));
+
+            // A shard only hangs up if it panicked, which join() passes on
+            let _ = self.sender.send(batch);
+        }
+    }
+}
+
+impl ShardedLedger {
+    pub fn with_config(config: Config, threads: usize) -> Result<Self, TransactionError> {
+        Self::from_ledger(Ledger::with_config(config), threads)
+    }
+
+    pub fn from_ledger(ledger: Ledger, threads: usize) -> Result<Self, TransactionError> {
+        let threads = threads.max(1);
+        let (outcome_sender, outcomes) = channel();
+
+        // Every shard is given them all, so each rejects a reused id just as
+        // the ledger would have
+        let mut shard_ledgers = (0..threads)
+            .map(|_| {
+                let mut shard_ledger = Ledger::with_config(ledger.config.clone());
+                shard_ledger.transaction_logs.evicted = ledger.transaction_logs.evicted.clone();
+                shard_ledger
+            })
+            .collect::<Vec<_>>();
+
+        let mut owners = HashMap::new();
+
+        for (client_id, account) in ledger.accounts.iter() {
+            shard_ledgers[*client_id as usize % threads]
+                .accounts
+                .insert(*client_id, account.clone());
+        }
+
+        for entry in ledger.transaction_logs.iter() {
+            let (transaction_id, transaction_log) = entry?;
+
+            owners.insert(transaction_id, transaction_log.client_id);
+
+            shard_ledgers[transaction_log.client_id as usize % threads]
+                .transaction_logs
+                .insert(transaction_id, transaction_log)?;
+        }
+
+        let shards = shard_ledgers
+            .into_iter()
+            .map(|shard_ledger| Self::spawn(shard_ledger, outcome_sender.clone()))
+            .collect();
+
+        Ok(ShardedLedger {
+            config: ledger.config,
+            shards,
+            owners,
+            evicted: ledger.transaction_logs.evicted,
+            outcomes,
+            rejected: Vec::new(),
+        })
+    }
+
+    fn spawn(mut ledger: Ledger, outcomes: Sender<Outcome>) -> Shard {
+        let (sender, receiver) = sync_channel::<Vec<Message>>(SHARD_QUEUE);
+
+        let handle = thread::spawn(move || {
+            for message in receiver.into_iter().flatten() {
+                match message {
+                    Message::Apply {
+                        line,
+                        transaction,
+                        record,
+                        foreign,
+                    } => {
+                        let result = adopt(&mut ledger.transaction_logs, transaction.id, foreign)
+                            .and_then(|_| ledger.apply(transaction));
+
+                        let outcome = Outcome {
+                            line,
+                            result,
+                            warnings: std::mem::take(&mut ledger.warnings),
+                            record,
+                        };
+
+                        if outcome.is_reportable() {
+                            // The dispatcher outlives every shard
+                            let _ = outcomes.send(outcome);
+                        }
+                    }
+                    Message::Metadata(client_metadata) => ledger.apply_metadata(client_metadata),
+                    Message::Find(transaction_id, reply) => {
+                        let _ = reply.send(ledger.transaction_logs.get(transaction_id));
+                    }
+                }
+            }
+
+            ledger
+        });
+
+        Shard {
+            sender,
+            batch: Vec::with_capacity(BATCH_SIZE),
+            handle,
+        }
+    }
+
+    fn shard(&self, client_id: ClientId) -> usize {
+        client_id as usize % self.shards.len()
+    }
+
+    fn send(&mut self, client_id: ClientId, message: Message) {
+        let index = self.shard(client_id);
+        let shard = &mut self.shards[index];
+
+        shard.batch.push(message);
+
+        if shard.batch.len() >= BATCH_SIZE {
+            shard.flush();
+        }
+    }
+
+    fn find(
+        &mut self,
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    ) -> Result<Option<TransactionLog>, TransactionError> {
+        let (reply, receiver) = sync_channel(1);
+        let index = self.shard(client_id);
+
+        self.shards[index]
+            .batch
+            .push(Message::Find(transaction_id, reply));
+        self.shards[index].flush();
+
+        receiver.recv().unwrap_or(Ok(None))
+    }
+
+    pub fn apply_metadata(&mut self, client_metadata: ClientMetadata) {
+        self.send(
+            client_metadata.client_id,
+            Message::Metadata(client_metadata),
+        );
+    }
+
+    pub fn apply_record(&mut self, line: u64, record: StringRecord) {
+        let transaction = Transaction::parse_record(&record, &self.config.money);
+
+        self.apply(line, transaction, record)
+    }
+
+    // Parses the normalized copy of a record, while any rejection reports the
+    // record as it was read
+    pub fn apply_normalized(&mut self, line: u64, normalized: &StringRecord, record: StringRecord) {
+        let transaction = Transaction::parse_record(normalized, &self.config.money);
+
+        self.apply(line, transaction, record)
+    }
+
+    fn apply(
+        &mut self,
+        line: u64,
+        transaction: Result<Transaction, TransactionError>,
+        record: StringRecord,
+    ) {
+        let transaction = match transaction {
+            Ok(transaction) => transaction,
+            Err(error) => {
+                self.reject(line, error, record);
+                return;
+            }
+        };
+
+        let foreign = match self.foreign(&transaction) {
+            Ok(foreign) => foreign,
+            Err(error) => {
+                self.reject(line, error, record);
+                return;
+            }
+        };
+
+        self.send(
+            transaction.client_id,
+            Message::Apply {
+                line,
+                transaction,
+                record,
+                foreign,
+            },
+        );
+    }
+
+    // The log of the transaction id if another client's shard holds it
+    fn foreign(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Option<TransactionLog>, TransactionError> {
+        if transaction.kind.is_admin() {
+            // Status changes don't use their transaction id
+            return Ok(None);
+        }
+
+        match self.owners.get(&transaction.id) {
+            None => {
+                if transaction.kind.has_amount() {
+                    self.owners.insert(transaction.id, transaction.client_id);
+                }
+
+                Ok(None)
+            }
+            Some(&owner) if owner == transaction.client_id => Ok(None),
+            Some(&owner) => {
+                // Ask the owning shard whether it actually logged the id,
+                // as the transaction which used it may have been rejected
+                let foreign = self.find(owner, transaction.id)?;
+
+                if foreign.is_none() && transaction.kind.has_amount() {
+                    self.owners.insert(transaction.id, transaction.client_id);
+                }
+
+                Ok(foreign)
+            }
+        }
+    }
+
+    pub fn reject(&mut self, line: u64, error: TransactionError, record: StringRecord) {
+        self.rejected.push(Outcome {
+            line,
+            result: Err(error),
+            warnings: Warnings::new(),
+            record,
+        });
+    }
+
+    pub fn poll(&mut self) -> Vec<Outcome> {
+        let mut outcomes = std::mem::take(&mut self.rejected);
+
+        outcomes.extend(self.outcomes.try_iter());
+
+        outcomes
+    }
+
+    pub fn finish(mut self) -> Result<(Ledger, Vec<Outcome>), TransactionError> {
+        let mut ledger = Ledger::with_config(self.config);
+        let shards = self.shards.len();
+
+        ledger.transaction_logs.evicted = self.evicted;
+
+        let mut outcomes = self.rejected;
+
+        for shard in self.shards.iter_mut() {
+            shard.flush();
+        }
+
+        for (index, shard) in self.shards.into_iter().enumerate() {
+            drop(shard.sender);
+
+            let mut shard_ledger = shard
+                .handle
+                .join()
+                .unwrap_or_else(|err| std::panic::resume_unwind(err));
+
+            ledger.accounts.extend(shard_ledger.accounts.drain());
+
+            for entry in shard_ledger.transaction_logs.iter() {
+                let (transaction_id, transaction_log) = entry?;
+
+                // Foreign logs were only copied in to check ownership
+                if transaction_log.client_id as usize % shards == index {
+                    ledger
+                        .transaction_logs
+                        .insert(transaction_id, transaction_log)?;
+                }
+            }
+        }
+
+        outcomes.extend(self.outcomes.try_iter());
+        outcomes.sort_by_key(|outcome| outcome.line);
+
+        Ok((ledger, outcomes))
+    }
+}
+
+// Copies in a log held by another shard so the shard can check ownership
+fn adopt(
+    transaction_logs: &mut TransactionLogs,
+    transaction_id: TransactionId,
+    foreign: Option<TransactionLog>,
+) -> Result<(), TransactionError> {
+    match foreign {
+        Some(transaction_log) if !transaction_logs.contains(transaction_id)? => {
+            transaction_logs.insert(transaction_id, transaction_log)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test_with_config {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let sharded_ledger = ShardedLedger::with_config(Config::new(), 4).unwrap();
+
+        assert!(sharded_ledger.shards.len() == 4);
+
+        let (ledger, outcomes) = sharded_ledger.finish().unwrap();
+
+        assert!(ledger == Ledger::new());
+        assert!(outcomes.is_empty());
+    }
+
+    #[test]
+    fn no_threads() {
+        let sharded_ledger = ShardedLedger::with_config(Config::new(), 0).unwrap();
+
+        assert!(sharded_ledger.shards.len() == 1);
+    }
+}
+
+#[cfg(test)]
+mod test_from_ledger {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let ledger = || {
+            let mut ledger = Ledger::new();
+
+            for client_id in 1..=3 {
+                assert!(
+                    ledger.apply(Transaction {
+                        id: client_id as TransactionId,
+                        client_id,
+                        kind: TransactionKind::Deposit,
+                        amount: amount!(1.0),
+                        currency: None,
+                        timestamp: None,
+                        reason: None,
+                    }) == Ok(())
+                );
+            }
+
+            ledger
+        };
+
+        let mut expected_ledger = ledger();
+
+        let mut sharded_ledger = ShardedLedger::from_ledger(ledger(), 2).unwrap();
+
+        //
+        // action
+        //
+
+        // Transaction id 1 was already used by client 1
+        for record in [
+            vec!["deposit", "2", "1", "1.0"],
+            vec!["dispute", "3", "3", ""],
+        ] {
+            let record = StringRecord::from(record);
+
+            let _ = expected_ledger.apply_record(&record);
+            sharded_ledger.apply_record(2, record);
+        }
+
+        let (ledger, outcomes) = sharded_ledger.finish().unwrap();
+
+        //
+        // test what we expect
+        //
+
+        assert!(ledger == expected_ledger);
+        assert!(outcomes.len() == 1);
+        assert!(outcomes[0].result == Err(TransactionError::DuplicateTransaction));
+    }
+}
+
+#[cfg(test)]
+mod test_evicted {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        //
+        // setup
+        //
+
+        let ledger = || {
+            let mut ledger = Ledger::new();
+
+            assert!(
+                ledger.apply(Transaction {
+                    id: 2,
+                    client_id: 1,
+                    kind: TransactionKind::Deposit,
+                    amount: amount!(3.0),
+                    currency: None,
+                    timestamp: None,
+                    reason: None,
+                }) == Ok(())
+            );
+
+            // As loaded from a snapshot of a run with a dispute window
+            ledger.transaction_logs.evicted.insert(1);
+
+            ledger
+        };
+
+        let mut expected_ledger = ledger();
+
+        let mut sharded_ledger = ShardedLedger::from_ledger(ledger(), 2).unwrap();
+
+        //
+        // action
+        //
+
+        for (line, record) in [
+            vec!["deposit", "1", "1", "5.0"],
+            vec!["deposit", "2", "1", "5.0"],
+            vec!["dispute", "1", "1", ""],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let record = StringRecord::from(record);
+
+            let _ = expected_ledger.apply_record(&record);
+            sharded_ledger.apply_record(line as u64 + 2, record);
+        }
+
+        let (ledger, outcomes) = sharded_ledger.finish().unwrap();
+
+        //
+        // test what we expect
+        //
+
+        assert!(ledger == expected_ledger);
+        assert!(ledger.transaction_logs.evicted.contains(1));
+        assert!(
+            outcomes
+                .iter()
+                .map(|outcome| outcome.result)
+                .collect::<Vec<_>>()
+                == vec![
+                    Err(TransactionError::DuplicateTransaction),
+                    Err(TransactionError::DuplicateTransaction),
+                    Err(TransactionError::DisputeWindowExpired),
+                ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_apply_normalized {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut sharded_ledger = ShardedLedger::with_config(Config::new(), 2).unwrap();
+
+        let record = StringRecord::from(vec!["ref-1", "withdrawal", "1", "1", "5.0"]);
+        let normalized = StringRecord::from(vec!["withdrawal", "1", "1", "5.0"]);
+
+        sharded_ledger.apply_normalized(2, &normalized, record.clone());
+
+        let (_, outcomes) = sharded_ledger.finish().unwrap();
+
+        // Reported as it was read, not as it was parsed
+        assert!(outcomes.len() == 1);
+        assert!(outcomes[0].result == Err(TransactionError::InsufficientFunds));
+        assert!(outcomes[0].record == record);
+    }
+}
+
+#[cfg(test)]
+mod test_apply_record {
+    use super::*;
+
+    fn records() -> Vec<StringRecord> {
+        [
+            "deposit,1,1,10.0",
+            "deposit,2,2,5.0",
+            // Rejected, so client 3 may still use transaction id 3
+            "withdrawal,1,3,50.0",
+            "deposit,3,3,7.0",
+            // Transaction id 1 belongs to client 1
+            "deposit,2,1,1.0",
+            "dispute,2,1,",
+            "dispute,3,2,",
+            "dispute,1,1,",
+            "chargeback,1,1,",
+            "deposit,1,4,1.0",
+            "bogus,1,5,1.0",
+            "withdrawal,2,6,2.5",
+            "dispute,3,3,",
+            "resolve,3,3,",
+        ]
+        .iter()
+        .map(|line| StringRecord::from(line.split(',').collect::<Vec<_>>()))
+        .collect()
+    }
+
+    #[test]
+    fn matches_ledger() {
+        //
+        // setup
+        //
+
+        let mut expected_ledger = Ledger::new();
+        let mut expected_outcomes = Vec::new();
+
+        for (line, record) in records().into_iter().enumerate() {
+            let result = expected_ledger.apply_record(&record);
+
+            if result.is_err() {
+                expected_outcomes.push((line as u64, result));
+            }
+        }
+
+        for storage in [Storage::Memory, Storage::Disk(std::env::temp_dir())] {
+            for threads in 1..=4 {
+                let config = Config {
+                    storage: storage.clone(),
+                    ..Config::new()
+                };
+
+                let mut sharded_ledger = ShardedLedger::with_config(config, threads).unwrap();
+
+                //
+                // action
+                //
+
+                for (line, record) in records().into_iter().enumerate() {
+                    sharded_ledger.apply_record(line as u64, record);
+                }
+
+                let (ledger, outcomes) = sharded_ledger.finish().unwrap();
+
+                //
+                // test what we expect
+                //
+
+                assert!(ledger.accounts == expected_ledger.accounts);
+                assert!(ledger.transaction_logs == expected_ledger.transaction_logs);
+
+                assert!(
+                    outcomes
+                        .iter()
+                        .map(|outcome| (outcome.line, outcome.result))
+                        .collect::<Vec<_>>()
+                        == expected_outcomes
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_poll {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut sharded_ledger = ShardedLedger::with_config(Config::new(), 2).unwrap();
+
+        sharded_ledger.apply_record(2, StringRecord::from(vec!["bogus", "1", "1"]));
+
+        let outcomes = sharded_ledger.poll();
+
+        assert!(outcomes.len() == 1);
+        assert!(outcomes[0].result == Err(TransactionError::UnknownTransactionType));
+        assert!(sharded_ledger.poll().is_empty());
+    }
+}
@@ -0,0 +1,534 @@
+use super::*;
+
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserializer, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+// Bump whenever the layout of the snapshot changes
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    accounts: &'a Accounts,
+    transaction_logs: &'a TransactionLogs,
+    evicted_transaction_ids: &'a SeenIds,
+}
+
+// Reads a snapshot into the given accounts and transaction logs, so the logs
+// go straight into their store, and returns its version
+struct SnapshotSeed<'a> {
+    accounts: &'a mut Accounts,
+    transaction_logs: &'a mut TransactionLogs,
+}
+
+impl<'de> DeserializeSeed<'de> for SnapshotSeed<'_> {
+    type Value = u32;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<u32, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SnapshotSeed<'_> {
+    type Value = u32;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a snapshot")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<u32, A::Error> {
+        let mut version = None;
+        let mut accounts = false;
+        let mut transaction_logs = false;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => version = Some(map.next_value()?),
+                "accounts" => {
+                    *self.accounts = map.next_value()?;
+                    accounts = true;
+                }
+                "transaction_logs" => {
+                    map.next_value_seed(&mut *self.transaction_logs)?;
+                    transaction_logs = true;
+                }
+                // Only in version 2 onwards
+                "evicted_transaction_ids" => self.transaction_logs.evicted = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        if !accounts {
+            return Err(de::Error::missing_field("accounts"));
+        }
+
+        if !transaction_logs {
+            return Err(de::Error::missing_field("transaction_logs"));
+        }
+
+        version.ok_or_else(|| de::Error::missing_field("version"))
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+    Replay(u64, TransactionError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "{err}"),
+            SnapshotError::Format(err) => write!(f, "invalid snapshot ({err})"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported snapshot version {version} (expected {SNAPSHOT_VERSION} or older)"
+            ),
+            SnapshotError::Replay(entry, err) => {
+                write!(f, "journal entry {entry} was refused ({err})")
+            }
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            SnapshotError::Io(err.into())
+        } else {
+            SnapshotError::Format(err)
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+impl Ledger {
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        serde_json::to_writer(
+            &mut writer,
+            &SnapshotRef {
+                version: SNAPSHOT_VERSION,
+                accounts: &self.accounts,
+                transaction_logs: &self.transaction_logs,
+                evicted_transaction_ids: &self.transaction_logs.evicted,
+            },
+        )?;
+
+        Ok(writer.flush()?)
+    }
+
+    pub fn load<R: Read>(&mut self, reader: R) -> Result<(), SnapshotError> {
+        let mut accounts = Accounts::new();
+        let mut transaction_logs = TransactionLogs::with_config(&self.config);
+
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+
+        let version = SnapshotSeed {
+            accounts: &mut accounts,
+            transaction_logs: &mut transaction_logs,
+        }
+        .deserialize(&mut deserializer)?;
+
+        deserializer.end()?;
+
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        self.accounts = accounts;
+        self.transaction_logs = transaction_logs;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_save {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let mut output = Vec::new();
+
+        assert!(Ledger::new().save(&mut output).is_ok());
+        assert!(output == br#"{"version":4,"accounts":{},"transaction_logs":{},"evicted_transaction_ids":[]}"#);
+    }
+
+    #[test]
+    fn ok() {
+        let mut ledger = Ledger::new();
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 7,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.2345),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let mut output = Vec::new();
+
+        assert!(ledger.save(&mut output).is_ok());
+
+        assert!(
+            String::from_utf8(output).unwrap()
+                == concat!(
+                    r#"{"version":4,"#,
+                    r#""accounts":{"2":{"wallets":[{"available":"1.2345","held":"0","debt":"0"}],"#,
+                    r#""status":"active","credit_limit":"0"}},"#,
+                    r#""transaction_logs":{"7":{"client_id":2,"kind":"deposit","#,
+                    r#""amount":"1.2345","state":"processed"}},"#,
+                    r#""evicted_transaction_ids":[]}"#
+                )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_load {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        //
+        // setup
+        //
+
+        let mut ledger = Ledger::new();
+
+        for (id, kind) in [
+            (1, TransactionKind::Deposit),
+            (2, TransactionKind::Withdrawal),
+        ] {
+            assert!(
+                ledger.apply(Transaction {
+                    id,
+                    client_id: 1,
+                    kind,
+                    amount: amount!(2.5),
+                    currency: None,
+                    timestamp: None,
+                    reason: None,
+                }) == Ok(())
+            );
+        }
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Dispute,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let mut snapshot = Vec::new();
+
+        assert!(ledger.save(&mut snapshot).is_ok());
+
+        //
+        // action
+        //
+
+        let mut loaded = Ledger::new();
+        let result = loaded.load(snapshot.as_slice());
+
+        //
+        // test what we expect
+        //
+
+        assert!(result.is_ok());
+        assert!(loaded == ledger);
+
+        // A resolve can reference the deposit of the previous run
+        assert!(
+            loaded.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Resolve,
+                amount: amount!(0.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut ledger = Ledger::new();
+        let result = ledger.load(&br#"{"version":5,"accounts":{},"transaction_logs":{}}"#[..]);
+
+        assert!(matches!(result, Err(SnapshotError::UnsupportedVersion(5))));
+    }
+
+    #[test]
+    fn version_one() {
+        let mut ledger = Ledger::new();
+        let result = ledger.load(&br#"{"version":1,"accounts":{},"transaction_logs":{}}"#[..]);
+
+        assert!(result.is_ok());
+        assert!(ledger == Ledger::new());
+    }
+
+    #[test]
+    fn version_two() {
+        //
+        // setup
+        //
+
+        let snapshot = concat!(
+            r#"{"version":2,"#,
+            r#""accounts":{"2":{"available":"1.5","held":"0.5","status":"locked","#,
+            r#""credit_limit":"10","debt":"0"}},"#,
+            r#""transaction_logs":{"7":{"client_id":2,"kind":"deposit","#,
+            r#""amount":"2","state":"disputed"}},"#,
+            r#""evicted_transaction_ids":[]}"#
+        );
+
+        //
+        // action
+        //
+
+        let mut ledger = Ledger::new();
+        let result = ledger.load(snapshot.as_bytes());
+
+        //
+        // test what we expect
+        //
+
+        assert!(result.is_ok());
+
+        // Accounts from before currencies have their balances in the unnamed wallet
+        assert!(
+            ledger.accounts.get(&2)
+                == Some(&Account {
+                    wallets: vec![Wallet {
+                        currency: None,
+                        available: amount!(1.5),
+                        held: amount!(0.5),
+                        debt: amount!(0),
+                    }],
+                    status: AccountStatus::Locked,
+                    credit_limit: amount!(10),
+                    base_currency: None,
+                })
+        );
+        assert!(ledger.transaction_logs.get(7).unwrap().map(|t| t.currency) == Some(None));
+    }
+
+    #[test]
+    fn currencies() {
+        let mut ledger = Ledger::new();
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: Currency::parse("GBP"),
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let mut snapshot = Vec::new();
+
+        assert!(ledger.save(&mut snapshot).is_ok());
+
+        let mut loaded = Ledger::new();
+
+        assert!(loaded.load(snapshot.as_slice()).is_ok());
+        assert!(loaded == ledger);
+    }
+
+    #[test]
+    fn conversions() {
+        let mut config = Config::new();
+
+        config.fx_rates.add(FxRate {
+            timestamp: 0,
+            from: Currency::parse("EUR").unwrap(),
+            to: Currency::parse("USD").unwrap(),
+            rate: amount!(1.1),
+        });
+
+        let mut ledger = Ledger::with_config(config.clone());
+
+        ledger.apply_metadata(ClientMetadata {
+            client_id: 1,
+            credit_limit: amount!(0),
+            base_currency: Currency::parse("USD"),
+        });
+
+        assert!(
+            ledger.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: Currency::parse("EUR"),
+                timestamp: Some(10),
+                reason: None,
+            }) == Ok(())
+        );
+
+        let mut snapshot = Vec::new();
+
+        assert!(ledger.save(&mut snapshot).is_ok());
+
+        let mut loaded = Ledger::with_config(config);
+
+        assert!(loaded.load(snapshot.as_slice()).is_ok());
+        assert!(loaded == ledger);
+    }
+
+    #[test]
+    fn evicted() {
+        //
+        // setup
+        //
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                window: Some(1),
+                ..DisputePolicy::new()
+            },
+            ..Config::new()
+        };
+
+        let mut ledger = Ledger::with_config(config.clone());
+
+        for id in 1..=3 {
+            assert!(
+                ledger.apply(Transaction {
+                    id,
+                    client_id: 1,
+                    kind: TransactionKind::Deposit,
+                    amount: amount!(1.0),
+                    currency: None,
+                    timestamp: None,
+                    reason: None,
+                }) == Ok(())
+            );
+        }
+
+        let mut snapshot = Vec::new();
+
+        assert!(ledger.save(&mut snapshot).is_ok());
+
+        //
+        // action
+        //
+
+        let mut loaded = Ledger::with_config(config);
+        let result = loaded.load(snapshot.as_slice());
+
+        //
+        // test what we expect
+        //
+
+        assert!(result.is_ok());
+        assert!(loaded == ledger);
+
+        // Ids evicted in the previous run still can't be reused
+        assert!(
+            loaded.apply(Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Err(TransactionError::DuplicateTransaction)
+        );
+    }
+
+    #[test]
+    fn window_order() {
+        //
+        // setup
+        //
+
+        let config = Config {
+            dispute_policy: DisputePolicy {
+                window: Some(20),
+                ..DisputePolicy::new()
+            },
+            ..Config::new()
+        };
+
+        let deposit = |id| Transaction {
+            id,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        let mut ledger = Ledger::with_config(config.clone());
+
+        for id in 1..=20 {
+            assert!(ledger.apply(deposit(id)) == Ok(()));
+        }
+
+        let mut snapshot = Vec::new();
+
+        assert!(ledger.save(&mut snapshot).is_ok());
+
+        //
+        // action
+        //
+
+        let mut loaded = Ledger::with_config(config);
+
+        assert!(loaded.load(snapshot.as_slice()).is_ok());
+        assert!(loaded.apply(deposit(21)) == Ok(()));
+
+        //
+        // test what we expect
+        //
+
+        // The oldest log is evicted, as it would have been without the reload
+        assert!(loaded.transaction_logs.evicted.ranges() == vec![(1, 1)]);
+
+        for id in 2..=21 {
+            assert!(loaded.transaction_logs.get(id).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let mut ledger = Ledger::new();
+        let result = ledger.load(&br#"{"version":1,"accounts":[]}"#[..]);
+
+        assert!(matches!(result, Err(SnapshotError::Format(_))));
+        assert!(ledger == Ledger::new());
+    }
+}
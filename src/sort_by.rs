@@ -0,0 +1,187 @@
+use super::*;
+
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortBy {
+    #[default]
+    Client,
+    Total,
+    Held,
+    Locked,
+}
+
+impl SortBy {
+    pub fn parse(sort_by: &str) -> Option<SortBy> {
+        match sort_by {
+            "client" => Some(SortBy::Client),
+            "total" => Some(SortBy::Total),
+            "held" => Some(SortBy::Held),
+            "locked" => Some(SortBy::Locked),
+            _ => None,
+        }
+    }
+
+    // Ties are always broken by client id then currency, so the order is
+    // total and stable; amounts are compared as they are, whatever their currency
+    pub fn compare(
+        &self,
+        a: &(ClientId, &Account, Wallet),
+        b: &(ClientId, &Account, Wallet),
+    ) -> Ordering {
+        let (a_client_id, a_account, a) = a;
+        let (b_client_id, b_account, b) = b;
+
+        let ordering = match self {
+            SortBy::Client => Ordering::Equal,
+            SortBy::Total => total(a).cmp(&total(b)),
+            SortBy::Held => a.held.cmp(&b.held),
+            SortBy::Locked => a_account.is_locked().cmp(&b_account.is_locked()),
+        };
+
+        ordering
+            .then(a_client_id.cmp(b_client_id))
+            .then(a.currency.cmp(&b.currency))
+    }
+}
+
+// Clamped the same as the total written out, which warns about it
+fn total(wallet: &Wallet) -> Amount {
+    wallet.available.saturating_add(wallet.held)
+}
+
+impl Accounts {
+    // A row per wallet of each account
+    pub fn sorted(&self, sort_by: SortBy) -> Vec<(ClientId, &Account, Wallet)> {
+        let mut rows = self
+            .iter()
+            .flat_map(|(client_id, account)| {
+                account
+                    .balances()
+                    .into_iter()
+                    .map(move |wallet| (*client_id, account, wallet))
+            })
+            .collect::<Vec<_>>();
+
+        rows.sort_by(|a, b| sort_by.compare(a, b));
+
+        rows
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(SortBy::parse("available").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(SortBy::parse("client") == Some(SortBy::Client));
+        assert!(SortBy::parse("total") == Some(SortBy::Total));
+        assert!(SortBy::parse("held") == Some(SortBy::Held));
+        assert!(SortBy::parse("locked") == Some(SortBy::Locked));
+    }
+}
+
+#[cfg(test)]
+mod test_sorted {
+    use super::*;
+
+    fn accounts() -> Accounts {
+        let mut accounts = Accounts::new();
+
+        for (client_id, available, held, status) in [
+            (3, amount!(1.0), amount!(2.0), AccountStatus::Active),
+            (1, amount!(5.0), amount!(0.0), AccountStatus::Locked),
+            (2, amount!(3.0), amount!(0.0), AccountStatus::Active),
+            (4, amount!(-1.0), amount!(1.0), AccountStatus::Frozen),
+        ] {
+            let mut account = accounts.find_or_create(client_id);
+
+            account.status = status;
+            account.set_wallet(Wallet {
+                available,
+                held,
+                ..Wallet::new(None)
+            });
+
+            accounts.insert(client_id, account);
+        }
+
+        accounts
+    }
+
+    fn client_ids(sort_by: SortBy) -> Vec<ClientId> {
+        accounts()
+            .sorted(sort_by)
+            .into_iter()
+            .map(|(client_id, _, _)| client_id)
+            .collect()
+    }
+
+    #[test]
+    fn client() {
+        assert!(client_ids(SortBy::default()) == vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn total() {
+        // Clients 2 and 3 are tied, so they are ordered by client id
+        assert!(client_ids(SortBy::Total) == vec![4, 2, 3, 1]);
+    }
+
+    #[test]
+    fn held() {
+        assert!(client_ids(SortBy::Held) == vec![1, 2, 4, 3]);
+    }
+
+    #[test]
+    fn locked() {
+        assert!(client_ids(SortBy::Locked) == vec![2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn currencies() {
+        //
+        // setup
+        //
+
+        let mut accounts = Accounts::new();
+        let mut account = accounts.find_or_create(1);
+
+        for (currency, available) in [("USD", amount!(1.0)), ("EUR", amount!(3.0))] {
+            account.set_wallet(Wallet {
+                available,
+                ..Wallet::new(Currency::parse(currency))
+            });
+        }
+
+        accounts.insert(1, account);
+        accounts.find_or_create(2);
+
+        //
+        // action
+        //
+
+        let rows = |sort_by| {
+            accounts
+                .sorted(sort_by)
+                .into_iter()
+                .map(|(client_id, _, wallet)| (client_id, wallet.currency))
+                .collect::<Vec<_>>()
+        };
+
+        //
+        // test what we expect
+        //
+
+        let (eur, usd) = (Currency::parse("EUR"), Currency::parse("USD"));
+
+        assert!(rows(SortBy::Client) == vec![(1, eur), (1, usd), (2, None)]);
+        assert!(rows(SortBy::Total) == vec![(2, None), (1, usd), (1, eur)]);
+    }
+}
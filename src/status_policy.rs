@@ -0,0 +1,122 @@
+use super::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatusPolicy {
+    pub frozen: Vec<TransactionKind>,
+    pub locked: Vec<TransactionKind>,
+}
+
+impl StatusPolicy {
+    pub fn new() -> Self {
+        // Open disputes can still be settled, so held funds are never stuck
+        StatusPolicy {
+            frozen: vec![TransactionKind::Resolve, TransactionKind::Chargeback],
+            locked: vec![TransactionKind::Resolve, TransactionKind::Chargeback],
+        }
+    }
+
+    pub fn allows(&self, status: AccountStatus, kind: TransactionKind) -> bool {
+        match status {
+            AccountStatus::Active => true,
+            AccountStatus::Frozen => self.frozen.contains(&kind),
+            AccountStatus::Locked => self.locked.contains(&kind),
+            AccountStatus::Closed => false,
+        }
+    }
+
+    pub fn parse_kinds(kinds: &str) -> Option<Vec<TransactionKind>> {
+        if kinds == "none" {
+            return Some(Vec::new());
+        }
+
+        kinds
+            .split(',')
+            .map(|kind| match TransactionKind::parse(kind.trim()) {
+                // Status changes are governed by AccountStatus::transition
+                Ok(kind) if !kind.is_admin() => Some(kind),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Default for StatusPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_new {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let status_policy = StatusPolicy::new();
+
+        assert!(
+            status_policy.frozen == vec![TransactionKind::Resolve, TransactionKind::Chargeback]
+        );
+        assert!(
+            status_policy.locked == vec![TransactionKind::Resolve, TransactionKind::Chargeback]
+        );
+    }
+
+    #[test]
+    fn default() {
+        assert!(StatusPolicy::default() == StatusPolicy::new());
+    }
+}
+
+#[cfg(test)]
+mod test_allows {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let status_policy = StatusPolicy {
+            frozen: vec![TransactionKind::Deposit],
+            locked: vec![TransactionKind::Resolve],
+        };
+
+        assert!(status_policy.allows(AccountStatus::Active, TransactionKind::Withdrawal));
+        assert!(status_policy.allows(AccountStatus::Frozen, TransactionKind::Deposit));
+        assert!(!status_policy.allows(AccountStatus::Frozen, TransactionKind::Resolve));
+        assert!(status_policy.allows(AccountStatus::Locked, TransactionKind::Resolve));
+        assert!(!status_policy.allows(AccountStatus::Locked, TransactionKind::Deposit));
+        assert!(!status_policy.allows(AccountStatus::Closed, TransactionKind::Resolve));
+    }
+}
+
+#[cfg(test)]
+mod test_parse_kinds {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(StatusPolicy::parse_kinds("deposit,bogus").is_none());
+        assert!(StatusPolicy::parse_kinds("").is_none());
+    }
+
+    #[test]
+    fn admin() {
+        assert!(StatusPolicy::parse_kinds("resolve,unlock").is_none());
+    }
+
+    #[test]
+    fn none() {
+        assert!(StatusPolicy::parse_kinds("none") == Some(Vec::new()));
+    }
+
+    #[test]
+    fn ok() {
+        assert!(
+            StatusPolicy::parse_kinds("deposit, resolve,chargeback")
+                == Some(vec![
+                    TransactionKind::Deposit,
+                    TransactionKind::Resolve,
+                    TransactionKind::Chargeback,
+                ])
+        );
+    }
+}
@@ -1,44 +1,86 @@
 use super::*;
 
 use csv::StringRecord;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: TransactionId,
     pub client_id: ClientId,
+    pub kind: TransactionKind,
     pub amount: Amount,
+    // Transactions without a currency go to the account's unnamed wallet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+    // When the transaction was made, which picks the exchange rate it converts at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<Timestamp>,
+    pub reason: Option<String>,
 }
 
 impl Transaction {
-    pub fn parse_record(record: &StringRecord) -> Option<Transaction> {
+    // Amounts are rounded to the configured precision as they are read
+    pub fn parse_record(
+        record: &StringRecord,
+        money: &Money,
+    ) -> Result<Transaction, TransactionError> {
         if record.len() < 3 {
-            return None;
+            return Err(TransactionError::MissingColumns);
         }
 
-        Some(Transaction {
+        let kind = TransactionKind::parse(&record[0])?;
+
+        Ok(Transaction {
             id: match String::from(&record[2]).parse() {
                 Ok(id) => id,
-                _ => return None,
+                _ => return Err(TransactionError::InvalidTransactionId),
             },
             client_id: match String::from(&record[1]).parse() {
                 Ok(client_id) => client_id,
-                _ => return None,
+                _ => return Err(TransactionError::InvalidClientId),
             },
-            amount: if record.len() < 4 {
-                Decimal::ZERO
+            kind,
+            amount: if !kind.has_amount() {
+                // Disputes, resolves and chargebacks refer to an existing amount
+                Amount::ZERO
+            } else if record.len() < 4 {
+                return Err(TransactionError::MissingColumns);
             } else {
-                match Decimal::from_str(&record[3]) {
-                    Ok(amount) => {
-                        if amount <= Decimal::ZERO {
-                            return None;
-                        }
-
-                        match Decimal::from_str(&format(amount)) {
-                            Ok(a) => a,
-                            _ => return None,
-                        }
+                match Amount::from_str(&record[3]) {
+                    Ok(amount) if amount <= Amount::ZERO => {
+                        return Err(TransactionError::NonPositiveAmount)
+                    }
+                    // Rounded away to nothing, it would be an empty transaction
+                    Ok(amount) if money.round(amount) <= Amount::ZERO => {
+                        return Err(TransactionError::NonPositiveAmount)
                     }
-                    _ => return None,
+                    Ok(amount) => money.round(amount),
+                    _ => return Err(TransactionError::InvalidAmount),
+                }
+            },
+            currency: match record.get(4) {
+                // Status changes apply to the whole account
+                _ if kind.is_admin() => None,
+                None | Some("") => None,
+                Some(code) => match Currency::parse(code) {
+                    Some(currency) => Some(currency),
+                    None => return Err(TransactionError::InvalidCurrency),
+                },
+            },
+            timestamp: match record.get(5) {
+                None | Some("") => None,
+                Some(timestamp) => match timestamp.parse() {
+                    Ok(timestamp) => Some(timestamp),
+                    _ => return Err(TransactionError::InvalidTimestamp),
+                },
+            },
+            reason: if !kind.is_admin() {
+                None
+            } else {
+                // Status changes must say why they were made
+                match record.get(3) {
+                    Some(reason) if !reason.is_empty() => Some(String::from(reason)),
+                    _ => return Err(TransactionError::MissingReason),
                 }
             },
         })
@@ -48,73 +90,221 @@ impl Transaction {
 #[cfg(test)]
 mod test_parse_record {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
     fn two_cols() {
         let record = StringRecord::from(vec!["desposit", "1"]);
-        let transaction = Transaction::parse_record(&StringRecord::from(record));
+        let transaction = Transaction::parse_record(&record, &Money::new());
 
-        assert!(transaction == None);
+        assert!(transaction == Err(TransactionError::MissingColumns));
+    }
+
+    #[test]
+    fn unknown_type() {
+        let record = StringRecord::from(vec!["desposit", "1", "1", "1.0"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::UnknownTransactionType));
     }
 
     #[test]
     fn invalid_id() {
-        let record = StringRecord::from(vec!["desposit", "invalid-id", "1", "1.0"]);
-        let transaction = Transaction::parse_record(&StringRecord::from(record));
+        let record = StringRecord::from(vec!["deposit", "1", "invalid-id", "1.0"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
 
-        assert!(transaction == None);
+        assert!(transaction == Err(TransactionError::InvalidTransactionId));
     }
 
     #[test]
     fn invalid_client_id() {
-        let record = StringRecord::from(vec!["desposit", "1", "invalid-client-id", "1.0"]);
-        let transaction = Transaction::parse_record(&StringRecord::from(record));
+        let record = StringRecord::from(vec!["deposit", "invalid-client-id", "1", "1.0"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
 
-        assert!(transaction == None);
+        assert!(transaction == Err(TransactionError::InvalidClientId));
     }
 
     #[test]
     fn three_cols() {
-        let record = StringRecord::from(vec!["despute", "1", "1"]);
-        let transaction = Transaction::parse_record(&StringRecord::from(record));
+        let record = StringRecord::from(vec!["dispute", "1", "1"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        let expected_transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        assert!(transaction == Ok(expected_transaction));
+    }
+
+    #[test]
+    fn three_cols_without_amount() {
+        let record = StringRecord::from(vec!["deposit", "1", "1"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::MissingColumns));
+    }
+
+    #[test]
+    fn four_cols_without_amount() {
+        let record = StringRecord::from(vec!["resolve", "1", "1", ""]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
 
         let expected_transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(0.0),
+            kind: TransactionKind::Resolve,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        assert!(transaction == Some(expected_transaction));
+        assert!(transaction == Ok(expected_transaction));
     }
 
     #[test]
     fn invalid_amount() {
-        let record = StringRecord::from(vec!["desposit", "1", "1", "invalid-amount"]);
-        let transaction = Transaction::parse_record(&StringRecord::from(record));
+        let record = StringRecord::from(vec!["deposit", "1", "1", "invalid-amount"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
 
-        assert!(transaction == None);
+        assert!(transaction == Err(TransactionError::InvalidAmount));
     }
 
     #[test]
     fn negative_amount() {
-        let record = StringRecord::from(vec!["despute", "1", "1", "-5.0"]);
-        let transaction = Transaction::parse_record(&StringRecord::from(record));
+        let record = StringRecord::from(vec!["withdrawal", "1", "1", "-5.0"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::NonPositiveAmount));
+    }
+
+    #[test]
+    fn admin_without_reason() {
+        let record = StringRecord::from(vec!["freeze", "1", "1"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::MissingReason));
+
+        let record = StringRecord::from(vec!["freeze", "1", "1", ""]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::MissingReason));
+    }
+
+    #[test]
+    fn admin() {
+        let record = StringRecord::from(vec!["unlock", "1", "7", "chargeback reviewed"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        let expected_transaction = Transaction {
+            id: 7,
+            client_id: 1,
+            kind: TransactionKind::Unlock,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: Some(String::from("chargeback reviewed")),
+        };
+
+        assert!(transaction == Ok(expected_transaction));
+    }
+
+    #[test]
+    fn invalid_currency() {
+        let record = StringRecord::from(vec!["deposit", "1", "1", "1.0", "EURO"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::InvalidCurrency));
+    }
+
+    #[test]
+    fn rounded_away() {
+        let record = StringRecord::from(vec!["deposit", "1", "1", "0.00001"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::NonPositiveAmount));
+    }
+
+    #[test]
+    fn precision() {
+        let record = StringRecord::from(vec!["deposit", "1", "1", "0.123456789"]);
+
+        let money = Money {
+            precision: 8,
+            rounding: Rounding::HalfUp,
+        };
+
+        let eight = Transaction::parse_record(&record, &money).map(|t| t.amount);
+        let four = Transaction::parse_record(&record, &Money::new()).map(|t| t.amount);
+
+        assert!(eight == Ok(amount!(0.12345679)));
+        assert!(four == Ok(amount!(0.1234)));
+    }
+
+    #[test]
+    fn invalid_timestamp() {
+        let record = StringRecord::from(vec!["deposit", "1", "1", "1.0", "", "yesterday"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        assert!(transaction == Err(TransactionError::InvalidTimestamp));
+    }
+
+    #[test]
+    fn timestamp() {
+        let record = StringRecord::from(vec!["deposit", "1", "1", "1.0", "", "1700000000"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        let expected_transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: Some(1700000000),
+            reason: None,
+        };
+
+        assert!(transaction == Ok(expected_transaction));
+    }
+
+    #[test]
+    fn currency() {
+        let record = StringRecord::from(vec!["dispute", "1", "1", "", "eur"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
+
+        let expected_transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: Currency::parse("EUR"),
+            timestamp: None,
+            reason: None,
+        };
 
-        assert!(transaction == None);
+        assert!(transaction == Ok(expected_transaction));
     }
 
     #[test]
     fn ok() {
-        let record = StringRecord::from(vec!["despute", "1", "1", "1.0"]);
-        let transaction = Transaction::parse_record(&StringRecord::from(record));
+        let record = StringRecord::from(vec!["deposit", "1", "1", "1.0"]);
+        let transaction = Transaction::parse_record(&record, &Money::new());
 
         let expected_transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(1.0),
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        assert!(transaction == Some(expected_transaction));
+        assert!(transaction == Ok(expected_transaction));
     }
 }
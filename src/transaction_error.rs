@@ -0,0 +1,161 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransactionError {
+    MalformedRecord,
+    MissingColumns,
+    UnknownTransactionType,
+    InvalidTransactionId,
+    InvalidClientId,
+    InvalidAmount,
+    NonPositiveAmount,
+    InvalidCurrency,
+    InvalidTimestamp,
+    MissingReason,
+    AccountNotFound,
+    AccountFrozen,
+    AccountLocked,
+    AccountClosed,
+    InvalidStatusChange,
+    DisputesOpen,
+    BalanceNotZero,
+    DuplicateTransaction,
+    InsufficientFunds,
+    TransactionNotFound,
+    TransactionNotOwned,
+    CurrencyMismatch,
+    RateNotFound,
+    DisputeNotFound,
+    DisputeWindowExpired,
+    DisputeNotAllowed,
+    AlreadyDisputed,
+    AlreadyChargedBack,
+    RedisputeNotAllowed,
+    Overflow,
+    Storage(io::ErrorKind),
+}
+
+impl TransactionError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TransactionError::MalformedRecord => "malformed_record",
+            TransactionError::MissingColumns => "missing_columns",
+            TransactionError::UnknownTransactionType => "unknown_transaction_type",
+            TransactionError::InvalidTransactionId => "invalid_transaction_id",
+            TransactionError::InvalidClientId => "invalid_client_id",
+            TransactionError::InvalidAmount => "invalid_amount",
+            TransactionError::NonPositiveAmount => "non_positive_amount",
+            TransactionError::InvalidCurrency => "invalid_currency",
+            TransactionError::InvalidTimestamp => "invalid_timestamp",
+            TransactionError::MissingReason => "missing_reason",
+            TransactionError::AccountNotFound => "account_not_found",
+            TransactionError::AccountFrozen => "account_frozen",
+            TransactionError::AccountLocked => "account_locked",
+            TransactionError::AccountClosed => "account_closed",
+            TransactionError::InvalidStatusChange => "invalid_status_change",
+            TransactionError::DisputesOpen => "disputes_open",
+            TransactionError::BalanceNotZero => "balance_not_zero",
+            TransactionError::DuplicateTransaction => "duplicate_transaction",
+            TransactionError::InsufficientFunds => "insufficient_funds",
+            TransactionError::TransactionNotFound => "transaction_not_found",
+            TransactionError::TransactionNotOwned => "transaction_not_owned",
+            TransactionError::CurrencyMismatch => "currency_mismatch",
+            TransactionError::RateNotFound => "rate_not_found",
+            TransactionError::DisputeNotFound => "dispute_not_found",
+            TransactionError::DisputeWindowExpired => "dispute_window_expired",
+            TransactionError::DisputeNotAllowed => "dispute_not_allowed",
+            TransactionError::AlreadyDisputed => "already_disputed",
+            TransactionError::AlreadyChargedBack => "already_charged_back",
+            TransactionError::RedisputeNotAllowed => "redispute_not_allowed",
+            TransactionError::Overflow => "overflow",
+            TransactionError::Storage(_) => "storage_failure",
+        }
+    }
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            TransactionError::MalformedRecord => "record could not be read",
+            TransactionError::MissingColumns => "record has too few columns",
+            TransactionError::UnknownTransactionType => "unknown transaction type",
+            TransactionError::InvalidTransactionId => "invalid transaction id",
+            TransactionError::InvalidClientId => "invalid client id",
+            TransactionError::InvalidAmount => "invalid amount",
+            TransactionError::NonPositiveAmount => "amount must be greater than zero",
+            TransactionError::InvalidCurrency => "invalid currency",
+            TransactionError::InvalidTimestamp => "invalid timestamp",
+            TransactionError::MissingReason => "a reason is required",
+            TransactionError::AccountNotFound => "account does not exist",
+            TransactionError::AccountFrozen => "account is frozen",
+            TransactionError::AccountLocked => "account is locked",
+            TransactionError::AccountClosed => "account is closed",
+            TransactionError::InvalidStatusChange => "account status does not allow this change",
+            TransactionError::DisputesOpen => "account still has funds held under dispute",
+            TransactionError::BalanceNotZero => "account still has a balance or debt",
+            TransactionError::DuplicateTransaction => "transaction id has already been used",
+            TransactionError::InsufficientFunds => "insufficient available funds",
+            TransactionError::TransactionNotFound => "referenced transaction does not exist",
+            TransactionError::TransactionNotOwned => {
+                "referenced transaction belongs to another client"
+            }
+            TransactionError::CurrencyMismatch => "referenced transaction is in another currency",
+            TransactionError::RateNotFound => "no exchange rate for the currency at that time",
+            TransactionError::DisputeNotFound => "referenced transaction is not under dispute",
+            TransactionError::DisputeWindowExpired => {
+                "referenced transaction is too old to be disputed"
+            }
+            TransactionError::DisputeNotAllowed => "referenced transaction cannot be disputed",
+            TransactionError::AlreadyDisputed => "referenced transaction is already under dispute",
+            TransactionError::AlreadyChargedBack => {
+                "referenced transaction has already been charged back"
+            }
+            TransactionError::RedisputeNotAllowed => {
+                "referenced transaction has already been resolved"
+            }
+            TransactionError::Overflow => "balance would overflow",
+            TransactionError::Storage(kind) => {
+                return write!(f, "transaction log storage failed ({kind})")
+            }
+        };
+
+        write!(f, "{message}")
+    }
+}
+
+impl Error for TransactionError {}
+
+impl From<io::Error> for TransactionError {
+    fn from(err: io::Error) -> Self {
+        TransactionError::Storage(err.kind())
+    }
+}
+
+#[cfg(test)]
+mod test_code {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        assert!(TransactionError::InsufficientFunds.code() == "insufficient_funds");
+        assert!(TransactionError::TransactionNotOwned.code() == "transaction_not_owned");
+        assert!(TransactionError::DisputesOpen.code() == "disputes_open");
+        assert!(TransactionError::BalanceNotZero.code() == "balance_not_zero");
+    }
+}
+
+#[cfg(test)]
+mod test_display {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        assert!(TransactionError::InsufficientFunds.to_string() == "insufficient available funds");
+        assert!(
+            TransactionError::Storage(io::ErrorKind::NotFound).to_string()
+                == "transaction log storage failed (entity not found)"
+        );
+    }
+}
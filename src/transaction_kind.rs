@@ -0,0 +1,152 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    Deposit,
+    Withdrawal,
+    Dispute,
+    Resolve,
+    Chargeback,
+    Freeze,
+    Unfreeze,
+    Unlock,
+    Close,
+}
+
+impl TransactionKind {
+    pub fn parse(kind: &str) -> Result<TransactionKind, TransactionError> {
+        match kind {
+            "deposit" => Ok(TransactionKind::Deposit),
+            "withdrawal" => Ok(TransactionKind::Withdrawal),
+            "dispute" => Ok(TransactionKind::Dispute),
+            "resolve" => Ok(TransactionKind::Resolve),
+            "chargeback" => Ok(TransactionKind::Chargeback),
+            "freeze" => Ok(TransactionKind::Freeze),
+            "unfreeze" => Ok(TransactionKind::Unfreeze),
+            "unlock" => Ok(TransactionKind::Unlock),
+            "close" => Ok(TransactionKind::Close),
+            _ => Err(TransactionError::UnknownTransactionType),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Deposit => "deposit",
+            TransactionKind::Withdrawal => "withdrawal",
+            TransactionKind::Dispute => "dispute",
+            TransactionKind::Resolve => "resolve",
+            TransactionKind::Chargeback => "chargeback",
+            TransactionKind::Freeze => "freeze",
+            TransactionKind::Unfreeze => "unfreeze",
+            TransactionKind::Unlock => "unlock",
+            TransactionKind::Close => "close",
+        }
+    }
+
+    pub fn has_amount(&self) -> bool {
+        matches!(self, TransactionKind::Deposit | TransactionKind::Withdrawal)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        matches!(
+            self,
+            TransactionKind::Freeze
+                | TransactionKind::Unfreeze
+                | TransactionKind::Unlock
+                | TransactionKind::Close
+        )
+    }
+}
+
+impl fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(
+            TransactionKind::parse("desposit") == Err(TransactionError::UnknownTransactionType)
+        );
+    }
+
+    #[test]
+    fn case_sensitive() {
+        assert!(TransactionKind::parse("Deposit") == Err(TransactionError::UnknownTransactionType));
+    }
+
+    #[test]
+    fn ok() {
+        assert!(TransactionKind::parse("deposit") == Ok(TransactionKind::Deposit));
+        assert!(TransactionKind::parse("withdrawal") == Ok(TransactionKind::Withdrawal));
+        assert!(TransactionKind::parse("dispute") == Ok(TransactionKind::Dispute));
+        assert!(TransactionKind::parse("resolve") == Ok(TransactionKind::Resolve));
+        assert!(TransactionKind::parse("chargeback") == Ok(TransactionKind::Chargeback));
+        assert!(TransactionKind::parse("freeze") == Ok(TransactionKind::Freeze));
+        assert!(TransactionKind::parse("unfreeze") == Ok(TransactionKind::Unfreeze));
+        assert!(TransactionKind::parse("unlock") == Ok(TransactionKind::Unlock));
+        assert!(TransactionKind::parse("close") == Ok(TransactionKind::Close));
+    }
+}
+
+#[cfg(test)]
+mod test_as_str {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for kind in [
+            TransactionKind::Deposit,
+            TransactionKind::Withdrawal,
+            TransactionKind::Dispute,
+            TransactionKind::Resolve,
+            TransactionKind::Chargeback,
+            TransactionKind::Freeze,
+            TransactionKind::Unfreeze,
+            TransactionKind::Unlock,
+            TransactionKind::Close,
+        ] {
+            assert!(TransactionKind::parse(kind.as_str()) == Ok(kind));
+            assert!(kind.to_string() == kind.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_has_amount {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        assert!(TransactionKind::Deposit.has_amount());
+        assert!(TransactionKind::Withdrawal.has_amount());
+        assert!(!TransactionKind::Dispute.has_amount());
+        assert!(!TransactionKind::Resolve.has_amount());
+        assert!(!TransactionKind::Chargeback.has_amount());
+        assert!(!TransactionKind::Freeze.has_amount());
+    }
+}
+
+#[cfg(test)]
+mod test_is_admin {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        assert!(!TransactionKind::Deposit.is_admin());
+        assert!(!TransactionKind::Chargeback.is_admin());
+        assert!(TransactionKind::Freeze.is_admin());
+        assert!(TransactionKind::Unfreeze.is_admin());
+        assert!(TransactionKind::Unlock.is_admin());
+        assert!(TransactionKind::Close.is_admin());
+    }
+}
@@ -0,0 +1,74 @@
+use super::*;
+
+use std::io;
+use std::path::PathBuf;
+
+pub type StoreEntry = io::Result<(TransactionId, TransactionLog)>;
+
+pub trait TransactionLogStore: Send {
+    fn get(&self, transaction_id: TransactionId) -> io::Result<Option<TransactionLog>>;
+
+    fn insert(
+        &mut self,
+        transaction_id: TransactionId,
+        transaction_log: TransactionLog,
+    ) -> io::Result<()>;
+
+    fn remove(&mut self, transaction_id: TransactionId) -> io::Result<()>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = StoreEntry> + '_>;
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Storage {
+    // Every log in a HashMap
+    #[default]
+    Memory,
+    // Logs in scratch files within the directory, see DiskStore
+    Disk(PathBuf),
+}
+
+impl Storage {
+    pub fn parse(storage: &str) -> Option<Storage> {
+        match storage {
+            "memory" => Some(Storage::Memory),
+            _ => match storage.strip_prefix("disk:") {
+                Some(directory) if !directory.is_empty() => {
+                    Some(Storage::Disk(PathBuf::from(directory)))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    pub fn open(&self) -> Box<dyn TransactionLogStore> {
+        match self {
+            Storage::Memory => Box::new(MemoryStore::new()),
+            Storage::Disk(directory) => Box::new(DiskStore::new(directory)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn unknown() {
+        assert!(Storage::parse("disk").is_none());
+        assert!(Storage::parse("disk:").is_none());
+        assert!(Storage::parse("tape:/dev/st0").is_none());
+    }
+
+    #[test]
+    fn ok() {
+        assert!(Storage::parse("memory") == Some(Storage::Memory));
+        assert!(Storage::parse("disk:/var/tmp") == Some(Storage::Disk(PathBuf::from("/var/tmp"))));
+    }
+}
@@ -1,53 +1,175 @@
 use super::*;
 
-use std::ops::{Deref, DerefMut};
+use serde::de::{self, DeserializeSeed, MapAccess, Visitor};
+use serde::ser::{self, SerializeMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::fmt;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct TransactionLog {
     pub client_id: ClientId,
+    pub kind: TransactionKind,
     pub amount: Amount,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+    // What the transaction was made in, when it was converted into the
+    // account's base currency above
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_amount: Option<Amount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_currency: Option<Currency>,
+    pub state: TransactionState,
 }
 
-#[derive(PartialEq)]
-pub struct TransactionLogs(pub HashMap<TransactionId, TransactionLog>);
+impl TransactionLog {
+    pub fn new(transaction: &Transaction) -> Self {
+        TransactionLog {
+            client_id: transaction.client_id,
+            kind: transaction.kind,
+            amount: transaction.amount,
+            currency: transaction.currency,
+            original_amount: None,
+            original_currency: None,
+            state: TransactionState::Processed,
+        }
+    }
+}
+
+pub struct TransactionLogs {
+    store: Box<dyn TransactionLogStore>,
+    // How many logs are kept for disputes, if limited
+    window: Option<usize>,
+    // Ids of the logs within the window, oldest first
+    order: VecDeque<TransactionId>,
+    // Ids of the logs evicted from the window, so they can't be reused
+    pub evicted: SeenIds,
+}
 
 impl TransactionLogs {
     pub fn new() -> Self {
-        TransactionLogs(HashMap::new())
+        Self::with_storage(&Storage::Memory)
     }
 
-    pub fn add(&mut self, transaction: &Transaction) {
-        self.insert(
-            transaction.id,
-            TransactionLog {
-                client_id: transaction.client_id,
-                amount: transaction.amount,
-            },
-        );
+    pub fn with_storage(storage: &Storage) -> Self {
+        TransactionLogs {
+            store: storage.open(),
+            window: None,
+            order: VecDeque::new(),
+            evicted: SeenIds::new(),
+        }
     }
 
-    pub fn find(&mut self, transaction: &Transaction) -> Option<TransactionLog> {
-        if let Some(transaction_log) = self.get(&transaction.id) {
-            if transaction_log.client_id == transaction.client_id {
-                return Some(*transaction_log);
+    pub fn with_config(config: &Config) -> Self {
+        TransactionLogs {
+            window: config.dispute_policy.window,
+            ..Self::with_storage(&config.storage)
+        }
+    }
+
+    pub fn add(&mut self, transaction: &Transaction) -> Result<(), TransactionError> {
+        self.insert(transaction.id, TransactionLog::new(transaction))
+    }
+
+    pub fn set_state(
+        &mut self,
+        transaction_id: TransactionId,
+        state: TransactionState,
+    ) -> Result<(), TransactionError> {
+        match self.get(transaction_id)? {
+            Some(transaction_log) => self.insert(
+                transaction_id,
+                TransactionLog {
+                    state,
+                    ..transaction_log
+                },
+            ),
+            None => Ok(()),
+        }
+    }
+
+    pub fn find(&mut self, transaction: &Transaction) -> Result<TransactionLog, TransactionError> {
+        match self.get(transaction.id)? {
+            Some(transaction_log) if transaction_log.client_id == transaction.client_id => {
+                Ok(transaction_log)
+            }
+            Some(_) => Err(TransactionError::TransactionNotOwned),
+            None if self.evicted.contains(transaction.id) => {
+                Err(TransactionError::DisputeWindowExpired)
             }
+            None => Err(TransactionError::TransactionNotFound),
         }
+    }
 
-        None
+    pub fn get(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Result<Option<TransactionLog>, TransactionError> {
+        Ok(self.store.get(transaction_id)?)
     }
-}
 
-impl Deref for TransactionLogs {
-    type Target = HashMap<TransactionId, TransactionLog>;
+    // Whether the id has been used, even if its log has since been evicted
+    pub fn contains(&self, transaction_id: TransactionId) -> Result<bool, TransactionError> {
+        Ok(self.evicted.contains(transaction_id) || self.get(transaction_id)?.is_some())
+    }
+
+    pub fn insert(
+        &mut self,
+        transaction_id: TransactionId,
+        transaction_log: TransactionLog,
+    ) -> Result<(), TransactionError> {
+        let window = match self.window {
+            Some(window) => window,
+            None => return Ok(self.store.insert(transaction_id, transaction_log)?),
+        };
+
+        if self.get(transaction_id)?.is_none() {
+            self.order.push_back(transaction_id);
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+        self.store.insert(transaction_id, transaction_log)?;
+        self.evict(window)
     }
-}
 
-impl DerefMut for TransactionLogs {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+    fn evict(&mut self, window: usize) -> Result<(), TransactionError> {
+        // Each log is looked at once at most, as disputed ones go to the back
+        for _ in 0..self.order.len() {
+            if self.order.len() <= window {
+                break;
+            }
+
+            let transaction_id = match self.order.pop_front() {
+                Some(transaction_id) => transaction_id,
+                None => break,
+            };
+
+            match self.get(transaction_id)? {
+                // Held funds must stay resolvable, so disputes keep their log
+                Some(transaction_log) if transaction_log.state == TransactionState::Disputed => {
+                    self.order.push_back(transaction_id)
+                }
+                _ => {
+                    self.store.remove(transaction_id)?;
+                    self.evicted.insert(transaction_id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = Result<(TransactionId, TransactionLog), TransactionError>> + '_ {
+        self.store.iter().map(|entry| Ok(entry?))
     }
 }
 
@@ -57,6 +179,69 @@ impl Default for TransactionLogs {
     }
 }
 
+impl PartialEq for TransactionLogs {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.evicted == other.evicted
+            && self.iter().all(|entry| match entry {
+                Ok((transaction_id, transaction_log)) => {
+                    other.get(transaction_id) == Ok(Some(transaction_log))
+                }
+                Err(_) => false,
+            })
+    }
+}
+
+impl Serialize for TransactionLogs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+
+        // Oldest first within a window, so loading them back rebuilds its order
+        let entries: Box<dyn Iterator<Item = _>> = match self.window {
+            Some(_) => Box::new(self.order.iter().filter_map(|transaction_id| {
+                self.get(*transaction_id)
+                    .transpose()
+                    .map(|entry| entry.map(|transaction_log| (*transaction_id, transaction_log)))
+            })),
+            None => Box::new(self.iter()),
+        };
+
+        for entry in entries {
+            let (transaction_id, transaction_log) = entry.map_err(ser::Error::custom)?;
+
+            map.serialize_entry(&transaction_id, &transaction_log)?;
+        }
+
+        map.end()
+    }
+}
+
+// Deserializes straight into the store, so a snapshot never has to fit in memory
+impl<'de> DeserializeSeed<'de> for &mut TransactionLogs {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for &mut TransactionLogs {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of transaction logs")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some((transaction_id, transaction_log)) = map.next_entry()? {
+            self.insert(transaction_id, transaction_log)
+                .map_err(de::Error::custom)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test_new {
     use super::*;
@@ -64,49 +249,173 @@ mod test_new {
     #[test]
     fn ok() {
         let transaction_logs = TransactionLogs::new();
-        assert!(transaction_logs == TransactionLogs(HashMap::new()));
+        assert!(transaction_logs.is_empty());
     }
 
     #[test]
     fn default() {
-        let transaction_logs = TransactionLogs::new();
-        assert!(transaction_logs == TransactionLogs(HashMap::new()));
+        let transaction_logs = TransactionLogs::default();
+        assert!(transaction_logs == TransactionLogs::new());
     }
 }
 
 #[cfg(test)]
 mod test_add {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
     fn ok() {
         let mut transaction_logs = TransactionLogs::new();
 
-        transaction_logs.add(&Transaction {
-            id: 1,
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let expected_transaction_log = TransactionLog {
             client_id: 1,
-            amount: dec!(1.0),
-        });
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            original_amount: None,
+            original_currency: None,
+            state: TransactionState::Processed,
+        };
 
-        let mut expected_transaction_logs = HashMap::new();
+        assert!(transaction_logs.len() == 1);
+        assert!(transaction_logs.get(1) == Ok(Some(expected_transaction_log)));
+    }
+}
 
-        expected_transaction_logs.insert(
-            1,
-            TransactionLog {
+#[cfg(test)]
+mod test_set_state {
+    use super::*;
+
+    #[test]
+    fn does_not_exist() {
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == TransactionLogs::new());
+    }
+
+    #[test]
+    fn ok() {
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
                 client_id: 1,
-                amount: dec!(1.0),
-            },
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
         );
 
-        assert!(*transaction_logs == expected_transaction_logs);
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(
+            transaction_logs.get(1).unwrap().map(|t| t.state) == Some(TransactionState::Disputed)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_insert {
+    use super::*;
+
+    fn transaction_log(state: TransactionState) -> TransactionLog {
+        TransactionLog {
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            original_amount: None,
+            original_currency: None,
+            state,
+        }
+    }
+
+    #[test]
+    fn window() {
+        //
+        // setup
+        //
+
+        let mut transaction_logs = TransactionLogs::with_config(&Config {
+            dispute_policy: DisputePolicy {
+                window: Some(2),
+                ..DisputePolicy::new()
+            },
+            ..Config::new()
+        });
+
+        //
+        // action
+        //
+
+        for id in 1..=3 {
+            assert!(
+                transaction_logs.insert(id, transaction_log(TransactionState::Processed)) == Ok(())
+            );
+        }
+
+        // Updating a log doesn't move it within the window
+        assert!(transaction_logs.set_state(2, TransactionState::Resolved) == Ok(()));
+
+        //
+        // test what we expect
+        //
+
+        assert!(transaction_logs.len() == 2);
+        assert!(transaction_logs.get(1) == Ok(None));
+        assert!(transaction_logs.contains(1) == Ok(true));
+        assert!(transaction_logs.evicted.ranges() == vec![(1, 1)]);
+
+        assert!(transaction_logs.insert(4, transaction_log(TransactionState::Processed)) == Ok(()));
+        assert!(transaction_logs.get(2) == Ok(None));
+        assert!(transaction_logs.evicted.ranges() == vec![(1, 2)]);
+    }
+
+    #[test]
+    fn window_keeps_disputed() {
+        let mut transaction_logs = TransactionLogs::with_config(&Config {
+            dispute_policy: DisputePolicy {
+                window: Some(1),
+                ..DisputePolicy::new()
+            },
+            ..Config::new()
+        });
+
+        assert!(transaction_logs.insert(1, transaction_log(TransactionState::Disputed)) == Ok(()));
+        assert!(transaction_logs.insert(2, transaction_log(TransactionState::Disputed)) == Ok(()));
+
+        assert!(transaction_logs.len() == 2);
+        assert!(transaction_logs.evicted.is_empty());
+
+        assert!(transaction_logs.set_state(1, TransactionState::Resolved) == Ok(()));
+        assert!(transaction_logs.insert(3, transaction_log(TransactionState::Processed)) == Ok(()));
+
+        // Only the log still under dispute is kept
+        assert!(transaction_logs.len() == 1);
+        assert!(transaction_logs.get(2).unwrap().is_some());
     }
 }
 
 #[cfg(test)]
 mod test_find {
     use super::*;
-    use rust_decimal_macros::dec;
 
     #[test]
     fn does_not_exist() {
@@ -115,79 +424,165 @@ mod test_find {
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(1.0),
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        assert!(transaction_logs.find(&transaction) == None);
+        assert!(transaction_logs.find(&transaction) == Err(TransactionError::TransactionNotFound));
     }
 
     #[test]
-    fn exists_but_not_owned() {
+    fn evicted() {
         let mut transaction_logs = TransactionLogs::new();
 
-        transaction_logs.add(&Transaction {
-            id: 1,
-            client_id: 2,
-            amount: dec!(1.0),
-        });
+        transaction_logs.evicted.insert(1);
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(1.0),
+            kind: TransactionKind::Dispute,
+            amount: amount!(0.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
-        assert!(transaction_logs.find(&transaction) == None);
+        assert!(transaction_logs.find(&transaction) == Err(TransactionError::DisputeWindowExpired));
     }
 
     #[test]
-    fn ok() {
+    fn exists_but_not_owned() {
         let mut transaction_logs = TransactionLogs::new();
 
-        transaction_logs.add(&Transaction {
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 2,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
+
+        let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(1.0),
-        });
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        assert!(transaction_logs.find(&transaction) == Err(TransactionError::TransactionNotOwned));
+    }
+
+    #[test]
+    fn ok() {
+        let mut transaction_logs = TransactionLogs::new();
+
+        assert!(
+            transaction_logs.add(&Transaction {
+                id: 1,
+                client_id: 1,
+                kind: TransactionKind::Deposit,
+                amount: amount!(1.0),
+                currency: None,
+                timestamp: None,
+                reason: None,
+            }) == Ok(())
+        );
 
         let transaction = Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(1.0),
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         };
 
         let expected_transaction_log = TransactionLog {
             client_id: 1,
-            amount: dec!(1.0),
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            original_amount: None,
+            original_currency: None,
+            state: TransactionState::Processed,
         };
 
-        assert!(transaction_logs.find(&transaction) == Some(expected_transaction_log));
+        assert!(transaction_logs.find(&transaction) == Ok(expected_transaction_log));
     }
 }
 
 #[cfg(test)]
-mod test_derefs {
+mod test_storage {
     use super::*;
-    use rust_decimal_macros::dec;
+    use std::io;
+    use std::path::PathBuf;
 
     #[test]
-    fn derefed_ok() {
-        let transaction_logs = TransactionLogs::new();
-        assert!(*transaction_logs == HashMap::new());
+    fn disk() {
+        //
+        // setup
+        //
+
+        let mut transaction_logs =
+            TransactionLogs::with_storage(&Storage::Disk(std::env::temp_dir()));
+
+        let transaction = Transaction {
+            id: 1,
+            client_id: 1,
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
+        };
+
+        //
+        // action
+        //
+
+        assert!(transaction_logs.add(&transaction) == Ok(()));
+        assert!(transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        //
+        // test what we expect
+        //
+
+        let mut expected_transaction_logs = TransactionLogs::new();
+
+        assert!(expected_transaction_logs.add(&transaction) == Ok(()));
+        assert!(expected_transaction_logs.set_state(1, TransactionState::Disputed) == Ok(()));
+
+        assert!(transaction_logs == expected_transaction_logs);
+        assert!(transaction_logs.contains(1) == Ok(true));
+        assert!(transaction_logs.contains(2) == Ok(false));
     }
 
     #[test]
-    fn derefedmut_ok() {
-        let mut transaction_logs = TransactionLogs::new();
+    fn failure() {
+        let mut transaction_logs =
+            TransactionLogs::with_storage(&Storage::Disk(PathBuf::from("/nonexistent/directory")));
 
-        transaction_logs.add(&Transaction {
+        let result = transaction_logs.add(&Transaction {
             id: 1,
             client_id: 1,
-            amount: dec!(1.0),
+            kind: TransactionKind::Deposit,
+            amount: amount!(1.0),
+            currency: None,
+            timestamp: None,
+            reason: None,
         });
 
-        transaction_logs.remove(&1);
-
-        assert!(*transaction_logs == HashMap::new());
+        assert!(result == Err(TransactionError::Storage(io::ErrorKind::NotFound)));
     }
 }
@@ -0,0 +1,136 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionState {
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack,
+}
+
+impl TransactionState {
+    pub fn transition(
+        &self,
+        kind: TransactionKind,
+        dispute_policy: &DisputePolicy,
+    ) -> Result<TransactionState, TransactionError> {
+        match (self, kind) {
+            (TransactionState::Processed, TransactionKind::Dispute) => {
+                Ok(TransactionState::Disputed)
+            }
+            (TransactionState::Resolved, TransactionKind::Dispute) => {
+                if dispute_policy.allow_redispute {
+                    Ok(TransactionState::Disputed)
+                } else {
+                    Err(TransactionError::RedisputeNotAllowed)
+                }
+            }
+            (TransactionState::Disputed, TransactionKind::Dispute) => {
+                Err(TransactionError::AlreadyDisputed)
+            }
+            (TransactionState::Disputed, TransactionKind::Resolve) => {
+                Ok(TransactionState::Resolved)
+            }
+            (TransactionState::Disputed, TransactionKind::Chargeback) => {
+                Ok(TransactionState::ChargedBack)
+            }
+            (TransactionState::ChargedBack, _) => Err(TransactionError::AlreadyChargedBack),
+            _ => Err(TransactionError::DisputeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_transition {
+    use super::*;
+
+    #[test]
+    fn processed() {
+        let dispute_policy = DisputePolicy::new();
+        let state = TransactionState::Processed;
+
+        assert!(
+            state.transition(TransactionKind::Dispute, &dispute_policy)
+                == Ok(TransactionState::Disputed)
+        );
+        assert!(
+            state.transition(TransactionKind::Resolve, &dispute_policy)
+                == Err(TransactionError::DisputeNotFound)
+        );
+        assert!(
+            state.transition(TransactionKind::Chargeback, &dispute_policy)
+                == Err(TransactionError::DisputeNotFound)
+        );
+    }
+
+    #[test]
+    fn disputed() {
+        let dispute_policy = DisputePolicy::new();
+        let state = TransactionState::Disputed;
+
+        assert!(
+            state.transition(TransactionKind::Dispute, &dispute_policy)
+                == Err(TransactionError::AlreadyDisputed)
+        );
+        assert!(
+            state.transition(TransactionKind::Resolve, &dispute_policy)
+                == Ok(TransactionState::Resolved)
+        );
+        assert!(
+            state.transition(TransactionKind::Chargeback, &dispute_policy)
+                == Ok(TransactionState::ChargedBack)
+        );
+    }
+
+    #[test]
+    fn resolved() {
+        let dispute_policy = DisputePolicy::new();
+        let state = TransactionState::Resolved;
+
+        assert!(
+            state.transition(TransactionKind::Dispute, &dispute_policy)
+                == Ok(TransactionState::Disputed)
+        );
+        assert!(
+            state.transition(TransactionKind::Resolve, &dispute_policy)
+                == Err(TransactionError::DisputeNotFound)
+        );
+        assert!(
+            state.transition(TransactionKind::Chargeback, &dispute_policy)
+                == Err(TransactionError::DisputeNotFound)
+        );
+    }
+
+    #[test]
+    fn resolved_without_redispute() {
+        let dispute_policy = DisputePolicy {
+            allow_redispute: false,
+            ..DisputePolicy::new()
+        };
+
+        assert!(
+            TransactionState::Resolved.transition(TransactionKind::Dispute, &dispute_policy)
+                == Err(TransactionError::RedisputeNotAllowed)
+        );
+    }
+
+    #[test]
+    fn charged_back() {
+        let dispute_policy = DisputePolicy::new();
+        let state = TransactionState::ChargedBack;
+
+        for kind in [
+            TransactionKind::Dispute,
+            TransactionKind::Resolve,
+            TransactionKind::Chargeback,
+        ] {
+            assert!(
+                state.transition(kind, &dispute_policy)
+                    == Err(TransactionError::AlreadyChargedBack)
+            );
+        }
+    }
+}
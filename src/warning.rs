@@ -0,0 +1,54 @@
+use super::*;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Warning {
+    Saturated {
+        transaction_id: TransactionId,
+        client_id: ClientId,
+    },
+    // Available plus held is beyond what an Amount can hold
+    SaturatedTotal {
+        client_id: ClientId,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::Saturated {
+                transaction_id,
+                client_id,
+            } => write!(
+                f,
+                "transaction {transaction_id} saturated a balance of client {client_id}"
+            ),
+            Warning::SaturatedTotal { client_id } => {
+                write!(f, "the total balance of client {client_id} saturated")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_display {
+    use super::*;
+
+    #[test]
+    fn saturated() {
+        let warning = Warning::Saturated {
+            transaction_id: 7,
+            client_id: 2,
+        };
+
+        assert!(warning.to_string() == "transaction 7 saturated a balance of client 2");
+    }
+
+    #[test]
+    fn saturated_total() {
+        let warning = Warning::SaturatedTotal { client_id: 2 };
+
+        assert!(warning.to_string() == "the total balance of client 2 saturated");
+    }
+}